@@ -0,0 +1,21 @@
+//! Generates the C API header and sets up linking of the Node.js addon
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "napi")]
+    napi_build::setup();
+    #[cfg(feature = "capi")]
+    {
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        println!("cargo:rerun-if-changed=src/c_api.rs");
+        println!("cargo:rerun-if-changed=src/lib.rs");
+        println!("cargo:rerun-if-env-changed=TXP_UPDATE_HEADER");
+        let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out = std::env::var("OUT_DIR").unwrap();
+        let bindings = cbindgen::generate(&dir).expect("the C API should be exportable");
+        // The committed `include/txp.h` is only rewritten on request, a test checks it's current
+        bindings.write_to_file(format!("{}/txp.h", out));
+        if matches!(std::env::var("TXP_UPDATE_HEADER").as_deref(), Ok("1")) {
+            bindings.write_to_file(format!("{}/include/txp.h", dir));
+        }
+    }
+}
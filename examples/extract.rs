@@ -15,22 +15,19 @@ struct Opt {
 }
 
 use std::fs::File;
-use std::io::Read;
 
 fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
     let opt = Opt::from_args();
-    let mut file = File::open(&opt.input)?;
-    let mut data = vec![];
-    file.read_to_end(&mut data)?;
-    let (_, atlas) = TextureAtlas::parse(&data).unwrap();
+    let file = TextureAtlas::open(&opt.input)?;
+    let atlas = file.atlas();
     let path = opt
         .input
         .parent()
         .unwrap()
         .join(opt.input.file_stem().unwrap());
-    std::fs::create_dir(&path);
+    std::fs::create_dir_all(&path)?;
     let ext = opt.ext.unwrap_or("png".into());
     for (i, tex) in atlas.0.into_iter().enumerate() {
         if ext == "dds" {
@@ -44,12 +41,12 @@ fn main() -> Result<()> {
                 let name = format!("tex{}.{}", i, ext);
                 let path = path.join(name);
                 let t = &tex.subtextures[0].mipmaps;
-                image_extract(t[0].clone(), path);
+                image_extract(t[0].clone(), path)?;
             } else {
                 for (j, side) in tex.subtextures.iter().enumerate() {
                     let name = format!("tex{}_sub{}.{}", i, j, ext);
                     let path = path.join(name);
-                    image_extract(side.mipmaps[0].clone(), path);
+                    image_extract(side.mipmaps[0].clone(), path)?;
                 }
             }
         }
@@ -58,8 +55,11 @@ fn main() -> Result<()> {
 }
 
 use std::path::Path;
-fn image_extract<Q: AsRef<Path>>(subtex: Mipmap<'_>, path: Q) -> Option<()> {
-    let image = subtex.to_dynamic_image()?;
-    image.flipv().save(path);
-    Some(())
+fn image_extract<Q: AsRef<Path>>(subtex: Mipmap<'_>, path: Q) -> Result<()> {
+    let format = subtex.format;
+    let image = subtex
+        .to_dynamic_image()
+        .ok_or_else(|| anyhow!("cannot decode {:?}", format))?;
+    image.flipv().save(path)?;
+    Ok(())
 }
@@ -10,10 +10,12 @@ struct Opt {
     /// Input file
     #[structopt(parse(from_os_str))]
     input: PathBuf,
-}
 
-use std::fs::File;
-use std::io::Read;
+    /// Print a JSON report instead of a table
+    #[cfg(feature = "serde")]
+    #[structopt(long)]
+    json: bool,
+}
 
 use std::io::Write;
 
@@ -21,10 +23,15 @@ use tabwriter::TabWriter;
 
 fn main() -> Result<()> {
     let opt = Opt::from_args();
-    let mut file = File::open(opt.input)?;
-    let mut data = vec![];
-    file.read_to_end(&mut data)?;
-    let (_, atlas) = TextureAtlas::parse(&data).unwrap();
+    let file = TextureAtlas::open(&opt.input)?;
+    #[cfg(feature = "serde")]
+    if opt.json {
+        let (_, report) = txp::report::AtlasReport::parse(file.as_bytes())
+            .map_err(|e| anyhow!("invalid atlas: {:?}", e.map(|e| e.code)))?;
+        println!("{}", report.to_json());
+        return Ok(());
+    }
+    let atlas = file.atlas();
     for (i, tex) in atlas.0.iter().enumerate() {
         println!("Texture #{}", i + 1);
         if tex.subtextures.len() == 1 {
@@ -43,14 +50,15 @@ fn print_mips(mips: &[Mipmap<'_>], tab: &str) -> Result<()> {
     let mut tw = TabWriter::new(vec![]);
     for (i, mip) in mips.iter().enumerate() {
         // println!("\t{}", mip);
-        write!(
+        writeln!(
             tw,
-            "\t{}#{}\t{}x{}\t{:?}\n",
+            "\t{}#{}\t{}x{}\t{:?}\tid {}",
             tab,
             i + 1,
             mip.width,
             mip.height,
-            mip.format
+            mip.format,
+            mip.id
         )?;
     }
     println!("{}", String::from_utf8(tw.into_inner()?)?);
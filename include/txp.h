@@ -0,0 +1,153 @@
+#ifndef TXP_H
+#define TXP_H
+
+/* Generated by cbindgen from src/c_api.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+enum TxpTextureFormat {
+  TXP_TEXTURE_FORMAT_A8 = 0,
+  TXP_TEXTURE_FORMAT_RGB8 = 1,
+  TXP_TEXTURE_FORMAT_RGBA8 = 2,
+  TXP_TEXTURE_FORMAT_RGB5 = 3,
+  TXP_TEXTURE_FORMAT_RGB5A1 = 4,
+  TXP_TEXTURE_FORMAT_RGBA4 = 5,
+  TXP_TEXTURE_FORMAT_DXT1 = 6,
+  TXP_TEXTURE_FORMAT_DXT1A = 7,
+  TXP_TEXTURE_FORMAT_DXT3 = 8,
+  TXP_TEXTURE_FORMAT_DXT5 = 9,
+  TXP_TEXTURE_FORMAT_ATI1 = 10,
+  TXP_TEXTURE_FORMAT_ATI2 = 11,
+  TXP_TEXTURE_FORMAT_L8 = 12,
+  TXP_TEXTURE_FORMAT_L8A8 = 13,
+  // Only available on MM+
+  TXP_TEXTURE_FORMAT_BC7 = 15,
+  // Only available on MM+
+  TXP_TEXTURE_FORMAT_BC6H = 127,
+};
+typedef uint32_t TxpTextureFormat;
+
+// How the subtextures and mips of a [`Texture`] are meant to be used, see [`Texture::kind`]
+enum TxpTextureKind {
+  // A single 2D image
+  TXP_TEXTURE_KIND_PLAIN,
+  // A single 2D image with its smaller mips
+  TXP_TEXTURE_KIND_MIPMAPPED,
+  // 6 subtextures, one per face
+  TXP_TEXTURE_KIND_CUBEMAP,
+  // Any other amount of subtextures
+  TXP_TEXTURE_KIND_ARRAY,
+  // Luma in the first ATI2 mip and chroma in the second, they aren't actual mips
+  TXP_TEXTURE_KIND_YUV,
+};
+typedef uint32_t TxpTextureKind;
+
+typedef enum {
+  TXP_ERROR_OK = 0,
+  // A null pointer, index out of range or buffer too small
+  TXP_ERROR_INVALID_ARGUMENT,
+  TXP_ERROR_IO,
+  // The input isn't a valid atlas, or a mip is too short to decode
+  TXP_ERROR_PARSE,
+  // The format can't be converted
+  TXP_ERROR_UNSUPPORTED,
+} TxpError;
+
+// An atlas owned by the library, free it with [`txp_atlas_free`]
+typedef struct TxpAtlas TxpAtlas;
+
+typedef struct {
+  uint32_t id;
+  uint32_t width;
+  uint32_t height;
+  TxpTextureFormat format;
+  // Length of the data in bytes
+  size_t size;
+} TxpMipInfo;
+
+// The reason the last call on this thread failed, `NULL` if it succeeded.
+//
+// The string is valid until the next call on this thread.
+const char *txp_last_error_message(void);
+
+// The error of the last call on this thread, [`TxpError::Ok`] if it succeeded
+TxpError txp_last_error_code(void);
+
+// Reads the atlas at `path`, which can be inside of a FARC (`archive.farc!/name_tex.bin`).
+//
+// Returns `NULL` on failure.
+//
+// # Safety
+// `path` must be a nul terminated string.
+TxpAtlas *txp_atlas_open(const char *path);
+
+// Parses an atlas out of `len` bytes, which are copied. Returns `NULL` on failure.
+//
+// # Safety
+// `data` must point to `len` readable bytes.
+TxpAtlas *txp_atlas_from_memory(const uint8_t *data, size_t len);
+
+// # Safety
+// `atlas` must come from this library and not be used afterwards, `NULL` is ignored.
+void txp_atlas_free(TxpAtlas *atlas);
+
+// Serializes the atlas as a little endian `_tex.bin` to `path`
+//
+// # Safety
+// `atlas` must be a valid atlas and `path` a nul terminated string.
+TxpError txp_atlas_write(const TxpAtlas *atlas, const char *path);
+
+// # Safety
+// `atlas` must be a valid atlas.
+size_t txp_atlas_texture_count(const TxpAtlas *atlas);
+
+// # Safety
+// `atlas` must be a valid atlas.
+size_t txp_texture_subtexture_count(const TxpAtlas *atlas, size_t texture);
+
+// The [`TextureKind`] of a texture, `-1` on failure
+//
+// # Safety
+// `atlas` must be a valid atlas.
+int32_t txp_texture_kind(const TxpAtlas *atlas, size_t texture);
+
+// # Safety
+// `atlas` must be a valid atlas.
+size_t txp_subtexture_mip_count(const TxpAtlas *atlas, size_t texture, size_t subtexture);
+
+// Fills `info` with the header of a mip
+//
+// # Safety
+// `atlas` must be a valid atlas and `info` writable.
+TxpError txp_mip_info(const TxpAtlas *atlas,
+                      size_t texture,
+                      size_t subtexture,
+                      size_t mip,
+                      TxpMipInfo *info);
+
+// The data of a mip as stored, owned by the atlas. Returns `NULL` on failure.
+//
+// # Safety
+// `atlas` must be a valid atlas, `len` is written when it isn't `NULL`.
+const uint8_t *txp_mip_data(const TxpAtlas *atlas,
+                            size_t texture,
+                            size_t subtexture,
+                            size_t mip,
+                            size_t *len);
+
+// Decodes a mip to `width * height * 4` bytes of RGBA into `out`
+//
+// # Safety
+// `atlas` must be a valid atlas and `out` point to `out_len` writable bytes.
+TxpError txp_mip_decode_rgba(const TxpAtlas *atlas,
+                             size_t texture,
+                             size_t subtexture,
+                             size_t mip,
+                             uint8_t *out,
+                             size_t out_len);
+
+#endif /* TXP_H */
@@ -0,0 +1,253 @@
+//! Finding every atlas under a directory and processing them in parallel.
+//!
+//! Files are detected by their magic, so both bare `_tex.bin` atlases and sprite sets
+//! (which embed an atlas) are found whatever their extension.
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use tracing::{debug, trace, warn};
+use walkdir::WalkDir;
+
+use super::read::parse_magic;
+use super::*;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FileKind {
+    /// A bare `_tex.bin`
+    Atlas,
+    /// A `spr_*.bin`, the offset of its atlas is stored right after the flags
+    SpriteSet,
+}
+
+/// A file containing an atlas
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Found {
+    pub path: PathBuf,
+    pub kind: FileKind,
+    /// Offset of the atlas inside of the file
+    pub offset: u64,
+}
+
+/// Outcome of [`process`], in the same order as the files
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub succeeded: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, io::Error)>,
+    /// Files which failed with [`io::ErrorKind::Unsupported`], e.g. formats without a decoder
+    pub unsupported: Vec<(PathBuf, io::Error)>,
+}
+
+impl Summary {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty() && self.unsupported.is_empty()
+    }
+
+    /// Moves the outcomes of `other` after the ones of `self`
+    pub fn append(&mut self, mut other: Summary) {
+        self.succeeded.append(&mut other.succeeded);
+        self.failed.append(&mut other.failed);
+        self.unsupported.append(&mut other.unsupported);
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} succeeded, {} failed, {} unsupported",
+            self.succeeded.len(),
+            self.failed.len(),
+            self.unsupported.len()
+        )?;
+        for (path, err) in &self.failed {
+            writeln!(f, "failed: {}: {}", path.display(), err)?;
+        }
+        for (path, err) in &self.unsupported {
+            writeln!(f, "unsupported: {}: {}", path.display(), err)?;
+        }
+        Ok(())
+    }
+}
+
+/// Detects whether `reader` holds an atlas, and where it starts
+pub fn detect<R: Read + Seek>(reader: &mut R) -> io::Result<Option<(FileKind, u64)>> {
+    let mut header = [0; 8];
+    match reader.read_exact(&mut header) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    if parse_magic(3)(&header).is_ok() {
+        return Ok(Some((FileKind::Atlas, 0)));
+    }
+    let raw = [header[4], header[5], header[6], header[7]];
+    for offset in [u32::from_le_bytes(raw), u32::from_be_bytes(raw)] {
+        if offset < 8 {
+            continue;
+        }
+        reader.seek(SeekFrom::Start(offset as u64))?;
+        let mut magic = [0; 4];
+        if reader.read_exact(&mut magic).is_ok() && parse_magic(3)(&magic).is_ok() {
+            return Ok(Some((FileKind::SpriteSet, offset as u64)));
+        }
+    }
+    Ok(None)
+}
+
+/// Walks `root` for files containing atlases, sorted by path
+///
+/// Files which can't be read are recorded as failed in the returned [`Summary`] and entries the
+/// walk can't reach are skipped with a warning, only an unreadable `root` is an error.
+#[tracing::instrument(skip(root), fields(root = %root.as_ref().display()))]
+pub fn scan<P: AsRef<Path>>(root: P) -> io::Result<(Vec<Found>, Summary)> {
+    let mut found = vec![];
+    let mut summary = Summary::default();
+    for entry in WalkDir::new(root).sort_by_file_name() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if e.depth() == 0 => return Err(e.into()),
+            Err(e) => {
+                warn!(error = %e, "skipping an entry the walk cannot read");
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let detected = File::open(entry.path()).and_then(|mut file| detect(&mut file));
+        match detected {
+            Ok(Some((kind, offset))) => {
+                trace!(path = %entry.path().display(), ?kind, offset);
+                found.push(Found {
+                    path: entry.into_path(),
+                    kind,
+                    offset,
+                });
+            }
+            Ok(None) => (),
+            Err(e) => summary.failed.push((entry.into_path(), e)),
+        }
+    }
+    debug!(count = found.len(), failed = summary.failed.len());
+    Ok((found, summary))
+}
+
+/// Parses every file in parallel and hands its atlas to `f`
+///
+/// ```no_run
+/// let (files, mut summary) = txp::batch::scan("rom/2d")?;
+/// summary.append(txp::batch::process(&files, |found, atlas| {
+///     println!("{}: {} textures", found.path.display(), atlas.0.len());
+///     Ok(())
+/// }));
+/// print!("{}", summary);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn process<F>(files: &[Found], f: F) -> Summary
+where
+    F: Fn(&Found, TextureAtlas<'_>) -> io::Result<()> + Sync,
+{
+    let results: Vec<_> = files
+        .par_iter()
+        .map(|found| (found, process_one(found, &f)))
+        .collect();
+    let mut summary = Summary::default();
+    for (found, result) in results {
+        let path = found.path.clone();
+        match result {
+            Ok(()) => summary.succeeded.push(path),
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => summary.unsupported.push((path, e)),
+            Err(e) => summary.failed.push((path, e)),
+        }
+    }
+    summary
+}
+
+fn process_one<F>(found: &Found, f: &F) -> io::Result<()>
+where
+    F: Fn(&Found, TextureAtlas<'_>) -> io::Result<()>,
+{
+    let data = std::fs::read(&found.path)?;
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let input = data
+        .get(found.offset as usize..)
+        .ok_or_else(|| invalid(format!("no atlas at {:#x}", found.offset)))?;
+    let (_, atlas) = TextureAtlas::parse(input).map_err(|e| {
+        // relative to the file, not to the embedded atlas
+        let msg = match (error_offset(input, &e), e) {
+            (Some(at), nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                format!(
+                    "invalid atlas: {:?} at {:#x}",
+                    e.code,
+                    found.offset as usize + at
+                )
+            }
+            _ => "truncated atlas".to_string(),
+        };
+        invalid(msg)
+    })?;
+    f(found, atlas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &[u8] = include_bytes!("../assets/mikitm001_tex.txp");
+
+    #[test]
+    fn scan_and_process() {
+        let root = std::env::temp_dir().join("txp_batch");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("spr")).unwrap();
+        std::fs::write(root.join("a_tex.bin"), INPUT).unwrap();
+        let mut sprite = vec![0; 16];
+        sprite[4..8].copy_from_slice(&16u32.to_le_bytes());
+        sprite.extend(INPUT);
+        std::fs::write(root.join("spr/spr_a.bin"), sprite).unwrap();
+        std::fs::write(root.join("readme.txt"), "not an atlas").unwrap();
+        std::fs::write(root.join("tiny"), "TXP").unwrap();
+
+        let (files, scanned) = scan(&root).unwrap();
+        assert!(scanned.is_success());
+        let kinds: Vec<_> = files.iter().map(|x| (x.kind, x.offset)).collect();
+        assert_eq!(kinds, [(FileKind::Atlas, 0), (FileKind::SpriteSet, 16)]);
+
+        let summary = process(&files, |found, atlas| {
+            assert_eq!(atlas.0.len(), 18);
+            match found.kind {
+                FileKind::Atlas => Ok(()),
+                FileKind::SpriteSet => Err(io::Error::new(io::ErrorKind::Unsupported, "sprites")),
+            }
+        });
+        assert_eq!(summary.succeeded, [root.join("a_tex.bin")]);
+        assert_eq!(summary.unsupported.len(), 1);
+        assert!(!summary.is_success());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn process_invalid() {
+        let path = std::env::temp_dir().join("txp_batch_invalid.bin");
+        std::fs::write(&path, &INPUT[..100]).unwrap();
+        let found = Found {
+            path: path.clone(),
+            kind: FileKind::Atlas,
+            offset: 0,
+        };
+        let summary = process(&[found], |_, _| Ok(()));
+        let (_, err) = &summary.failed[0];
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().ends_with("at 0x54"), "{}", err);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn scan_missing_root() {
+        let root = std::env::temp_dir().join("txp_batch_missing");
+        assert!(scan(root).is_err());
+    }
+}
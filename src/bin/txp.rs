@@ -0,0 +1,613 @@
+//! Command line tool to inspect, extract and rebuild `_tex.bin` atlases, see `txp --help`
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use structopt::StructOpt;
+use tabwriter::TabWriter;
+use txp::*;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "txp",
+    about = "Inspect, extract and rebuild `_tex.bin` texture atlases"
+)]
+enum Opt {
+    /// Print the textures of an atlas
+    Info {
+        /// Input atlas, `archive.farc!/name_tex.bin` reads from a FARC archive
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        /// Print a JSON report of the layout instead of a table
+        #[structopt(long)]
+        json: bool,
+        #[structopt(flatten)]
+        select: Selection,
+    },
+    /// Save textures as images or DDS files
+    Extract {
+        /// Input atlas, `archive.farc!/name_tex.bin` reads from a FARC archive
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        /// Output directory, defaults to a directory named after the input
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+        /// `png` (or any extension supported by `image`), `dds`,
+        /// or `manifest` to extract everything needed by `pack`
+        #[structopt(short, long, default_value = "png")]
+        format: String,
+        /// File names, using `{stem}`, `{texture}`, `{subtexture}`, `{sub}` (`_sub{subtexture}`
+        /// for arrays, empty otherwise), `{format}` and `{ext}`
+        #[structopt(short, long, default_value = "tex{texture}{sub}.{ext}")]
+        name: String,
+        #[structopt(flatten)]
+        select: Selection,
+    },
+    /// Extract every atlas and sprite set found under a directory, in parallel
+    Batch {
+        #[structopt(parse(from_os_str))]
+        root: PathBuf,
+        /// Output directory, mirroring the layout of `root`
+        #[structopt(short, long, parse(from_os_str))]
+        output: PathBuf,
+        /// Same as for `extract`
+        #[structopt(short, long, default_value = "png")]
+        format: String,
+        /// Same as for `extract`
+        #[structopt(short, long, default_value = "tex{texture}{sub}.{ext}")]
+        name: String,
+    },
+    /// Rebuild an atlas from a directory made by `extract --format manifest`
+    Pack {
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Re-encode textures to another format
+    Convert {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        /// Target format, e.g. `DXT5` or `RGBA8`
+        #[structopt(short, long, parse(try_from_str = parse_format))]
+        format: TextureFormat,
+        #[structopt(flatten)]
+        select: Selection,
+    },
+    /// Check that atlases are well formed
+    Validate {
+        #[structopt(required = true, parse(from_os_str))]
+        inputs: Vec<PathBuf>,
+    },
+    /// Compare two atlases, exits with 1 when they differ
+    Diff {
+        #[structopt(parse(from_os_str))]
+        old: PathBuf,
+        #[structopt(parse(from_os_str))]
+        new: PathBuf,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct Selection {
+    /// Only use these textures, e.g. `0,2,5-7`
+    #[structopt(short, long)]
+    textures: Option<Ranges>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Ranges(Vec<(usize, usize)>);
+
+impl FromStr for Ranges {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let index = |x: &str| {
+            x.trim()
+                .parse()
+                .map_err(|_| format!("`{}` is not a texture index", x))
+        };
+        s.split(',')
+            .map(|x| match x.split_once('-') {
+                Some((start, end)) => Ok((index(start)?, index(end)?)),
+                None => index(x).map(|i| (i, i)),
+            })
+            .collect::<Result<_, _>>()
+            .map(Ranges)
+    }
+}
+
+impl Selection {
+    /// Indices of the selected textures out of `count`
+    fn indices(&self, count: usize) -> Result<Vec<usize>> {
+        let ranges = match &self.textures {
+            Some(ranges) => ranges,
+            None => return Ok((0..count).collect()),
+        };
+        let mut indices = vec![];
+        for &(start, end) in &ranges.0 {
+            if start > end || end >= count {
+                bail!(
+                    "textures {}-{} out of range, there are {}",
+                    start,
+                    end,
+                    count
+                );
+            }
+            indices.extend(start..=end);
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        Ok(indices)
+    }
+}
+
+fn parse_format(s: &str) -> Result<TextureFormat, String> {
+    (0..=u8::MAX as u32)
+        .filter_map(TextureFormat::from_id)
+        .find(|x| format!("{:?}", x).eq_ignore_ascii_case(s))
+        .ok_or_else(|| format!("unknown texture format `{}`", s))
+}
+
+/// Replaces every `{key}` of `template`
+fn render(template: &str, vars: &[(&str, String)]) -> Result<String> {
+    let mut name = template.to_string();
+    for (key, value) in vars {
+        name = name.replace(&format!("{{{}}}", key), value);
+    }
+    if let Some(start) = name.find('{') {
+        bail!(
+            "unknown placeholder in `{}` at `{}`",
+            template,
+            &name[start..]
+        );
+    }
+    Ok(name)
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    farc::read(path).with_context(|| format!("cannot read {}", path.display()))
+}
+
+/// Describes a parse failure without dumping the remaining input
+fn parse_error(input: &[u8], err: nom::Err<nom::error::Error<&[u8]>>) -> String {
+    match (error_offset(input, &err), err) {
+        (Some(offset), nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            format!("{:?} failed at offset {:#x}", e.code, offset)
+        }
+        _ => "unexpected end of file".into(),
+    }
+}
+
+fn parse<'a>(input: &'a [u8], path: &Path) -> Result<TextureAtlas<'a>> {
+    TextureAtlas::parse(input)
+        .map(|(_, atlas)| atlas)
+        .map_err(|e| {
+            anyhow!(
+                "{} is not a valid atlas: {}",
+                path.display(),
+                parse_error(input, e)
+            )
+        })
+}
+
+fn write(atlas: &TextureAtlas<'_>, path: &Path) -> Result<()> {
+    let bytes = atlas.try_to_bytes().context("cannot serialize the atlas")?;
+    std::fs::write(path, bytes).with_context(|| format!("cannot write {}", path.display()))
+}
+
+/// Name of the input without extensions or FARC prefix, e.g. `mikitm001_tex`
+fn stem(path: &Path) -> String {
+    let name = path.to_string_lossy();
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    name.split('.').next().unwrap_or_default().to_string()
+}
+
+fn info(input: &Path, json: bool, select: &Selection) -> Result<()> {
+    let data = read(input)?;
+    let atlas = parse(&data, input)?;
+    if json {
+        let (_, mut report) = report::AtlasReport::parse(&data)
+            .map_err(|e| anyhow!("{}: {}", input.display(), parse_error(&data, e)))?;
+        let indices = select.indices(report.textures.len())?;
+        report.textures = indices
+            .into_iter()
+            .map(|i| report.textures[i].clone())
+            .collect();
+        println!("{}", report.to_json());
+        return Ok(());
+    }
+    let mut tw = TabWriter::new(std::io::stdout());
+    for i in select.indices(atlas.0.len())? {
+        let tex = &atlas.0[i];
+        writeln!(tw, "Texture #{} ({:?})", i, tex.kind())?;
+        for (j, sub) in tex.subtextures.iter().enumerate() {
+            if tex.subtextures.len() > 1 {
+                writeln!(tw, "\tSubtexture #{}", j)?;
+            }
+            for (k, mip) in sub.mipmaps.iter().enumerate() {
+                writeln!(
+                    tw,
+                    "\t#{}\t{}x{}\t{:?}\tid {}\t{} bytes",
+                    k,
+                    mip.width,
+                    mip.height,
+                    mip.format,
+                    mip.id,
+                    mip.data.len()
+                )?;
+            }
+        }
+    }
+    tw.flush()?;
+    Ok(())
+}
+
+fn extract(
+    input: &Path,
+    output: Option<PathBuf>,
+    format: &str,
+    template: &str,
+    select: &Selection,
+) -> Result<ExitCode> {
+    let data = read(input)?;
+    let atlas = parse(&data, input)?;
+    let indices = select.indices(atlas.0.len())?;
+    let stem = stem(input);
+    let dir = output.unwrap_or_else(|| {
+        let parent = input.parent().filter(|x| !x.as_os_str().is_empty());
+        parent.unwrap_or(Path::new(".")).join(&stem)
+    });
+    let skipped = export(&atlas, &indices, &dir, &stem, format, template)?;
+    for skip in &skipped {
+        eprintln!("{}", skip);
+    }
+    if !skipped.is_empty() {
+        eprintln!("{} image(s) could not be extracted", skipped.len());
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Saves the textures at `indices` to `dir`, returns what couldn't be saved as an image
+fn export(
+    atlas: &TextureAtlas<'_>,
+    indices: &[usize],
+    dir: &Path,
+    stem: &str,
+    format: &str,
+    template: &str,
+) -> Result<Vec<String>> {
+    std::fs::create_dir_all(dir).with_context(|| format!("cannot create {}", dir.display()))?;
+
+    if format == "manifest" {
+        let selected = indices.iter().map(|&i| atlas.0[i].clone()).collect();
+        manifest::unpack(&TextureAtlas(selected), dir)
+            .with_context(|| format!("cannot unpack to {}", dir.display()))?;
+        return Ok(vec![]);
+    }
+
+    let mut skipped = vec![];
+    for &i in indices {
+        let tex = &atlas.0[i];
+        let first = tex.subtextures.first().and_then(|x| x.mipmaps.first());
+        let tex_format = first.map(|x| format!("{:?}", x.format)).unwrap_or_default();
+        let name = |j: usize, sub: String| {
+            render(
+                template,
+                &[
+                    ("stem", stem.to_string()),
+                    ("texture", i.to_string()),
+                    ("subtexture", j.to_string()),
+                    ("sub", sub),
+                    ("format", tex_format.clone()),
+                    ("ext", format.to_string()),
+                ],
+            )
+        };
+        if format == "dds" {
+            let path = dir.join(name(0, String::new())?);
+            let dds = tex
+                .to_dds()
+                .with_context(|| format!("cannot convert texture {} to dds", i))?;
+            let mut file =
+                File::create(&path).with_context(|| format!("cannot create {}", path.display()))?;
+            dds.write(&mut file)
+                .with_context(|| format!("cannot write {}", path.display()))?;
+            continue;
+        }
+        let images = tex.to_images();
+        for (j, image) in images.iter().enumerate() {
+            let suffix = match images.len() {
+                1 => String::new(),
+                _ => format!("_sub{}", j),
+            };
+            let path = dir.join(name(j, suffix)?);
+            let image = match image {
+                Some(image) => image,
+                None => {
+                    skipped.push(format!(
+                        "texture {} ({}) cannot be saved as an image, try `--format dds`",
+                        i, tex_format
+                    ));
+                    continue;
+                }
+            };
+            image
+                .flipv()
+                .save(&path)
+                .with_context(|| format!("cannot save {}", path.display()))?;
+        }
+    }
+    Ok(skipped)
+}
+
+fn batch(root: &Path, output: &Path, format: &str, template: &str) -> Result<ExitCode> {
+    let (files, mut summary) =
+        batch::scan(root).with_context(|| format!("cannot scan {}", root.display()))?;
+    summary.append(batch::process(&files, |found, atlas| {
+        let relative = found.path.strip_prefix(root).unwrap_or(&found.path);
+        let stem = stem(relative);
+        let dir = output
+            .join(relative.parent().unwrap_or(Path::new("")))
+            .join(&stem);
+        let indices: Vec<_> = (0..atlas.0.len()).collect();
+        match export(&atlas, &indices, &dir, &stem, format, template) {
+            Ok(skipped) if skipped.is_empty() => Ok(()),
+            Ok(skipped) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                skipped.join(", "),
+            )),
+            Err(e) => Err(io::Error::other(format!("{:#}", e))),
+        }
+    }));
+    print!("{}", summary);
+    Ok(match summary.is_success() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    })
+}
+
+fn convert(input: &Path, output: &Path, format: TextureFormat, select: &Selection) -> Result<()> {
+    let data = read(input)?;
+    let mut atlas = parse(&data, input)?;
+    for i in select.indices(atlas.0.len())? {
+        convert_texture(&mut atlas.0[i], format).with_context(|| format!("texture {}", i))?;
+    }
+    write(&atlas, output)
+}
+
+/// Re-encodes every subtexture of `tex` as `format`.
+///
+/// YUV textures have no actual mips, the image they display becomes a single full mip chain
+fn convert_texture(tex: &mut Texture, format: TextureFormat) -> Result<()> {
+    if tex.kind() == TextureKind::Yuv {
+        let image = tex
+            .to_images()
+            .remove(0)
+            .context("cannot decode the YUV mips")?;
+        let (width, height) = image::GenericImageView::dimensions(&image);
+        let levels = 32 - width.max(height).leading_zeros();
+        *tex = Texture::from_image(&image, format, levels)
+            .ok_or_else(|| anyhow!("cannot encode {:?}", format))?;
+        return Ok(());
+    }
+    for sub in &mut tex.subtextures {
+        // smaller mips are regenerated from the largest one
+        let first = sub.mipmaps.first().cloned().unwrap_or_default();
+        let image = first
+            .to_dynamic_image()
+            .ok_or_else(|| anyhow!("{:?} cannot be decoded", sub.mipmaps[0].format))?;
+        for mip in &mut sub.mipmaps {
+            let resized = image.resize_exact(
+                mip.width.max(1),
+                mip.height.max(1),
+                image::imageops::FilterType::Triangle,
+            );
+            let encoded = Mipmap::from_image(&resized, format)
+                .ok_or_else(|| anyhow!("cannot encode {:?}", format))?;
+            mip.format = format;
+            mip.data = encoded.data;
+        }
+    }
+    Ok(())
+}
+
+/// Problems with the atlas at `path`, empty when it's valid
+fn problems(path: &Path) -> Result<Vec<String>> {
+    let data = read(path)?;
+    let atlas = match TextureAtlas::parse(&data) {
+        Ok((_, atlas)) => atlas,
+        Err(e) => return Ok(vec![format!("cannot be parsed: {}", parse_error(&data, e))]),
+    };
+    let mut problems = vec![];
+    for (i, tex) in atlas.0.iter().enumerate() {
+        let first = match tex.subtextures.first().and_then(|x| x.mipmaps.first()) {
+            Some(first) => first,
+            None => {
+                problems.push(format!("texture {} is empty", i));
+                continue;
+            }
+        };
+        for (j, sub) in tex.subtextures.iter().enumerate() {
+            for (k, mip) in sub.mipmaps.iter().enumerate() {
+                let (w, h) = (first.width >> k, first.height >> k);
+                // the smallest mips are sometimes clamped to 1 pixel
+                if (mip.width, mip.height) != (w, h)
+                    && (mip.width, mip.height) != (w.max(1), h.max(1))
+                {
+                    problems.push(format!(
+                        "texture {} subtexture {} mip {} is {}x{}, expected {}x{}",
+                        i, j, k, mip.width, mip.height, w, h
+                    ));
+                }
+                if mip.format != first.format {
+                    problems.push(format!(
+                        "texture {} subtexture {} mip {} is {:?}, expected {:?}",
+                        i, j, k, mip.format, first.format
+                    ));
+                }
+            }
+        }
+    }
+    if let Err(e) = atlas.validate() {
+        problems.push(format!("cannot be written back: {}", e));
+    }
+    Ok(problems)
+}
+
+fn validate(inputs: &[PathBuf]) -> Result<ExitCode> {
+    let mut code = ExitCode::SUCCESS;
+    for input in inputs {
+        let problems = problems(input)?;
+        if problems.is_empty() {
+            println!("{}: ok", input.display());
+            continue;
+        }
+        code = ExitCode::FAILURE;
+        for problem in problems {
+            println!("{}: {}", input.display(), problem);
+        }
+    }
+    Ok(code)
+}
+
+fn describe(mip: &Mipmap<'_>) -> String {
+    format!(
+        "{}x{} {:?} id {}",
+        mip.width, mip.height, mip.format, mip.id
+    )
+}
+
+fn diff(old: &Path, new: &Path) -> Result<ExitCode> {
+    let (old_data, new_data) = (read(old)?, read(new)?);
+    let (old, new) = (parse(&old_data, old)?, parse(&new_data, new)?);
+    let mut lines = vec![];
+    if old.0.len() != new.0.len() {
+        lines.push(format!("{} -> {} textures", old.0.len(), new.0.len()));
+    }
+    for (i, (a, b)) in old.0.iter().zip(&new.0).enumerate() {
+        if a.subtextures.len() != b.subtextures.len() {
+            lines.push(format!(
+                "texture {}: {} -> {} subtextures",
+                i,
+                a.subtextures.len(),
+                b.subtextures.len()
+            ));
+        }
+        for (j, (a, b)) in a.subtextures.iter().zip(&b.subtextures).enumerate() {
+            if a.mipmaps.len() != b.mipmaps.len() {
+                lines.push(format!(
+                    "texture {} subtexture {}: {} -> {} mips",
+                    i,
+                    j,
+                    a.mipmaps.len(),
+                    b.mipmaps.len()
+                ));
+            }
+            for (k, (a, b)) in a.mipmaps.iter().zip(&b.mipmaps).enumerate() {
+                let (x, y) = (describe(a), describe(b));
+                if x != y {
+                    lines.push(format!(
+                        "texture {} subtexture {} mip {}: {} -> {}",
+                        i, j, k, x, y
+                    ));
+                } else if a.data != b.data {
+                    lines.push(format!(
+                        "texture {} subtexture {} mip {}: data differs",
+                        i, j, k
+                    ));
+                }
+            }
+        }
+    }
+    for line in &lines {
+        println!("{}", line);
+    }
+    Ok(match lines.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    })
+}
+
+fn main() -> Result<ExitCode> {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    match Opt::from_args() {
+        Opt::Info {
+            input,
+            json,
+            select,
+        } => info(&input, json, &select).map(|_| ExitCode::SUCCESS),
+        Opt::Extract {
+            input,
+            output,
+            format,
+            name,
+            select,
+        } => extract(&input, output, &format, &name, &select),
+        Opt::Batch {
+            root,
+            output,
+            format,
+            name,
+        } => batch(&root, &output, &format, &name),
+        Opt::Pack { dir, output } => {
+            let atlas = manifest::repack(&dir)
+                .with_context(|| format!("cannot repack {}", dir.display()))?;
+            write(&atlas, &output).map(|_| ExitCode::SUCCESS)
+        }
+        Opt::Convert {
+            input,
+            output,
+            format,
+            select,
+        } => convert(&input, &output, format, &select).map(|_| ExitCode::SUCCESS),
+        Opt::Validate { inputs } => validate(&inputs),
+        Opt::Diff { old, new } => diff(&old, &new),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_textures() {
+        let select = Selection {
+            textures: Some("4,0-2,1".parse().unwrap()),
+        };
+        assert_eq!(select.indices(5).unwrap(), vec![0, 1, 2, 4]);
+        assert!(select.indices(4).is_err());
+        assert!("1-x".parse::<Ranges>().is_err());
+    }
+
+    #[test]
+    fn render_template() {
+        let vars = [("texture", "3".to_string()), ("sub", String::new())];
+        assert_eq!(render("tex{texture}{sub}.png", &vars).unwrap(), "tex3.png");
+        assert!(render("{mip}.png", &vars).is_err());
+        assert_eq!(parse_format("dxt5"), Ok(TextureFormat::DXT5));
+    }
+
+    #[test]
+    fn convert_yuv() {
+        let input = include_bytes!("../../assets/spr_sel_pv616.bin");
+        let (_, atlas) = TextureAtlas::parse(input).unwrap();
+        let mut tex = atlas.0[0].clone();
+        convert_texture(&mut tex, TextureFormat::RGBA8).unwrap();
+        assert_eq!(tex.kind(), TextureKind::Mipmapped);
+        let mips = &tex.subtextures[0].mipmaps;
+        assert_eq!(mips.len(), 11);
+        assert_eq!((mips[1].width, mips[1].height), (512, 256));
+        let expected = atlas.0[0].to_images().remove(0).unwrap().to_rgba8();
+        assert_eq!(&mips[0].data[..], &expected.as_raw()[..]);
+    }
+}
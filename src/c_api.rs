@@ -0,0 +1,368 @@
+//! A C ABI over atlases, for C, C++ and C# tools linking the `cdylib`.
+//!
+//! The header is generated to `include/txp.h` by `build.rs`. Every function reports failure
+//! through its return value, the reason is kept per thread in [`txp_last_error_message`].
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::raw::c_char;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use super::*;
+
+/// An atlas owned by the library, free it with [`txp_atlas_free`]
+pub struct TxpAtlas(TextureAtlas<'static>);
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TxpError {
+    Ok = 0,
+    /// A null pointer, index out of range or buffer too small
+    InvalidArgument,
+    Io,
+    /// The input isn't a valid atlas, or a mip is too short to decode
+    Parse,
+    /// The format can't be converted
+    Unsupported,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct TxpMipInfo {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    /// Length of the data in bytes
+    pub size: usize,
+}
+
+struct Error(TxpError, String);
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        let code = match err.kind() {
+            io::ErrorKind::InvalidInput => TxpError::InvalidArgument,
+            io::ErrorKind::InvalidData => TxpError::Parse,
+            io::ErrorKind::Unsupported => TxpError::Unsupported,
+            _ => TxpError::Io,
+        };
+        Self(code, err.to_string())
+    }
+}
+
+fn invalid(msg: &str) -> Error {
+    Error(TxpError::InvalidArgument, msg.to_string())
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<(TxpError, CString)>> = const { RefCell::new(None) };
+}
+
+/// Keeps the error of a call for [`txp_last_error_message`], clearing it on success
+fn record<T>(result: &Result<T, Error>) {
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = result.as_ref().err().map(|Error(code, msg)| {
+            let msg = CString::new(msg.replace('\0', "")).expect("nul bytes were removed");
+            (*code, msg)
+        })
+    });
+}
+
+/// Runs `f`, returning `default` when it fails
+fn ffi<T>(default: T, f: impl FnOnce() -> Result<T, Error>) -> T {
+    let result = f();
+    record(&result);
+    result.unwrap_or(default)
+}
+
+/// Runs `f`, returning the code of its error
+fn status(f: impl FnOnce() -> Result<(), Error>) -> TxpError {
+    let result = f();
+    record(&result);
+    result.err().map_or(TxpError::Ok, |Error(code, _)| code)
+}
+
+unsafe fn atlas<'a>(atlas: *const TxpAtlas) -> Result<&'a TextureAtlas<'static>, Error> {
+    atlas
+        .as_ref()
+        .map(|x| &x.0)
+        .ok_or_else(|| invalid("atlas is null"))
+}
+
+unsafe fn texture<'a>(ptr: *const TxpAtlas, texture: usize) -> Result<&'a Texture<'static>, Error> {
+    atlas(ptr)?
+        .0
+        .get(texture)
+        .ok_or_else(|| invalid("texture index out of range"))
+}
+
+unsafe fn mipmap<'a>(
+    ptr: *const TxpAtlas,
+    tex: usize,
+    subtexture: usize,
+    mip: usize,
+) -> Result<&'a Mipmap<'static>, Error> {
+    texture(ptr, tex)?
+        .subtextures
+        .get(subtexture)
+        .ok_or_else(|| invalid("subtexture index out of range"))?
+        .mipmaps
+        .get(mip)
+        .ok_or_else(|| invalid("mipmap index out of range"))
+}
+
+unsafe fn path<'a>(path: *const c_char) -> Result<&'a Path, Error> {
+    if path.is_null() {
+        return Err(invalid("path is null"));
+    }
+    let path = CStr::from_ptr(path)
+        .to_str()
+        .map_err(|_| invalid("path isn't UTF-8"))?;
+    Ok(Path::new(path))
+}
+
+fn parse(data: &[u8]) -> Result<*mut TxpAtlas, Error> {
+    let (_, atlas) = TextureAtlas::parse(data).map_err(|e| {
+        let msg = match crate::error_offset(data, &e) {
+            Some(offset) => format!("invalid atlas at {:#x}", offset),
+            None => "truncated atlas".to_string(),
+        };
+        Error(TxpError::Parse, msg)
+    })?;
+    Ok(Box::into_raw(Box::new(TxpAtlas(atlas.into_owned()))))
+}
+
+/// The reason the last call on this thread failed, `NULL` if it succeeded.
+///
+/// The string is valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn txp_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some((_, msg)) => msg.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// The error of the last call on this thread, [`TxpError::Ok`] if it succeeded
+#[no_mangle]
+pub extern "C" fn txp_last_error_code() -> TxpError {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(TxpError::Ok, |x| x.0))
+}
+
+/// Reads the atlas at `path`, which can be inside of a FARC (`archive.farc!/name_tex.bin`).
+///
+/// Returns `NULL` on failure.
+///
+/// # Safety
+/// `path` must be a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn txp_atlas_open(path: *const c_char) -> *mut TxpAtlas {
+    ffi(ptr::null_mut(), || {
+        let path = self::path(path)?;
+        #[cfg(feature = "farc")]
+        let data = farc::read(path)?;
+        #[cfg(not(feature = "farc"))]
+        let data = std::fs::read(path)?;
+        parse(&data)
+    })
+}
+
+/// Parses an atlas out of `len` bytes, which are copied. Returns `NULL` on failure.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn txp_atlas_from_memory(data: *const u8, len: usize) -> *mut TxpAtlas {
+    ffi(ptr::null_mut(), || {
+        if data.is_null() {
+            return Err(invalid("data is null"));
+        }
+        parse(slice::from_raw_parts(data, len))
+    })
+}
+
+/// # Safety
+/// `atlas` must come from this library and not be used afterwards, `NULL` is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn txp_atlas_free(atlas: *mut TxpAtlas) {
+    if !atlas.is_null() {
+        drop(Box::from_raw(atlas));
+    }
+}
+
+/// Serializes the atlas as a little endian `_tex.bin` to `path`
+///
+/// # Safety
+/// `atlas` must be a valid atlas and `path` a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn txp_atlas_write(atlas: *const TxpAtlas, path: *const c_char) -> TxpError {
+    status(|| {
+        let bytes = self::atlas(atlas)?.try_to_bytes()?;
+        std::fs::write(self::path(path)?, bytes)?;
+        Ok(())
+    })
+}
+
+/// # Safety
+/// `atlas` must be a valid atlas.
+#[no_mangle]
+pub unsafe extern "C" fn txp_atlas_texture_count(atlas: *const TxpAtlas) -> usize {
+    ffi(0, || Ok(self::atlas(atlas)?.0.len()))
+}
+
+/// # Safety
+/// `atlas` must be a valid atlas.
+#[no_mangle]
+pub unsafe extern "C" fn txp_texture_subtexture_count(
+    atlas: *const TxpAtlas,
+    texture: usize,
+) -> usize {
+    ffi(0, || Ok(self::texture(atlas, texture)?.subtextures.len()))
+}
+
+/// The [`TextureKind`] of a texture, `-1` on failure
+///
+/// # Safety
+/// `atlas` must be a valid atlas.
+#[no_mangle]
+pub unsafe extern "C" fn txp_texture_kind(atlas: *const TxpAtlas, texture: usize) -> i32 {
+    ffi(-1, || Ok(self::texture(atlas, texture)?.kind() as i32))
+}
+
+/// # Safety
+/// `atlas` must be a valid atlas.
+#[no_mangle]
+pub unsafe extern "C" fn txp_subtexture_mip_count(
+    atlas: *const TxpAtlas,
+    texture: usize,
+    subtexture: usize,
+) -> usize {
+    ffi(0, || {
+        let tex = self::texture(atlas, texture)?;
+        let sub = tex
+            .subtextures
+            .get(subtexture)
+            .ok_or_else(|| invalid("subtexture index out of range"))?;
+        Ok(sub.mipmaps.len())
+    })
+}
+
+/// Fills `info` with the header of a mip
+///
+/// # Safety
+/// `atlas` must be a valid atlas and `info` writable.
+#[no_mangle]
+pub unsafe extern "C" fn txp_mip_info(
+    atlas: *const TxpAtlas,
+    texture: usize,
+    subtexture: usize,
+    mip: usize,
+    info: *mut TxpMipInfo,
+) -> TxpError {
+    status(|| {
+        let mip = mipmap(atlas, texture, subtexture, mip)?;
+        let info = info.as_mut().ok_or_else(|| invalid("info is null"))?;
+        *info = TxpMipInfo {
+            id: mip.id,
+            width: mip.width,
+            height: mip.height,
+            format: mip.format,
+            size: mip.data.len(),
+        };
+        Ok(())
+    })
+}
+
+/// The data of a mip as stored, owned by the atlas. Returns `NULL` on failure.
+///
+/// # Safety
+/// `atlas` must be a valid atlas, `len` is written when it isn't `NULL`.
+#[no_mangle]
+pub unsafe extern "C" fn txp_mip_data(
+    atlas: *const TxpAtlas,
+    texture: usize,
+    subtexture: usize,
+    mip: usize,
+    len: *mut usize,
+) -> *const u8 {
+    ffi(ptr::null(), || {
+        let mip = mipmap(atlas, texture, subtexture, mip)?;
+        if let Some(len) = len.as_mut() {
+            *len = mip.data.len();
+        }
+        Ok(mip.data.as_ptr())
+    })
+}
+
+/// Decodes a mip to `width * height * 4` bytes of RGBA into `out`
+///
+/// # Safety
+/// `atlas` must be a valid atlas and `out` point to `out_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn txp_mip_decode_rgba(
+    atlas: *const TxpAtlas,
+    texture: usize,
+    subtexture: usize,
+    mip: usize,
+    out: *mut u8,
+    out_len: usize,
+) -> TxpError {
+    status(|| {
+        let mip = mipmap(atlas, texture, subtexture, mip)?;
+        let size = mip.width as usize * mip.height as usize * 4;
+        if out.is_null() || out_len < size {
+            return Err(invalid("output buffer is too small"));
+        }
+        let rgba = mip.decode_rgba8()?;
+        slice::from_raw_parts_mut(out, size).copy_from_slice(&rgba);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_is_current() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/txp.h"));
+        let committed = include_str!("../include/txp.h");
+        assert!(
+            generated == committed,
+            "include/txp.h is stale, rebuild with TXP_UPDATE_HEADER=1"
+        );
+    }
+
+    #[test]
+    fn open_and_decode() {
+        let path = CString::new("assets/mikitm001_tex.txp").unwrap();
+        unsafe {
+            let atlas = txp_atlas_open(path.as_ptr());
+            assert!(!atlas.is_null());
+            assert_eq!(txp_atlas_texture_count(atlas), 18);
+            assert_eq!(txp_texture_kind(atlas, 1), TextureKind::Mipmapped as i32);
+
+            let mut info = TxpMipInfo::default();
+            assert_eq!(txp_mip_info(atlas, 0, 0, 0, &mut info), TxpError::Ok);
+            assert_eq!((info.width, info.height, info.size), (256, 8, 256 * 8 * 3));
+            let mut rgba = vec![0; 256 * 8 * 4];
+            let err = txp_mip_decode_rgba(atlas, 0, 0, 0, rgba.as_mut_ptr(), rgba.len());
+            assert_eq!(err, TxpError::Ok);
+            assert!(txp_last_error_message().is_null());
+
+            let err = txp_mip_decode_rgba(atlas, 0, 0, 0, rgba.as_mut_ptr(), 16);
+            assert_eq!(err, TxpError::InvalidArgument);
+            assert_eq!(txp_texture_subtexture_count(atlas, 18), 0);
+            let msg = CStr::from_ptr(txp_last_error_message());
+            assert_eq!(msg.to_str().unwrap(), "texture index out of range");
+            txp_atlas_free(atlas);
+
+            let garbage = [0u8; 16];
+            assert!(txp_atlas_from_memory(garbage.as_ptr(), garbage.len()).is_null());
+            assert_eq!(txp_last_error_code(), TxpError::Parse);
+        }
+    }
+}
@@ -1,10 +1,9 @@
-use ddsfile;
 use ddsfile::AlphaMode;
 use ddsfile::D3D10ResourceDimension;
 use ddsfile::Dds;
 use ddsfile::NewD3dParams;
 use ddsfile::{D3DFormat, DxgiFormat};
-use tracing::{debug, instrument};
+use tracing::debug;
 
 use std::convert::TryInto;
 
@@ -22,8 +21,8 @@ impl Texture<'_> {
         let def = Default::default();
         let first = self
             .subtextures
-            .get(0)
-            .and_then(|x| x.mipmaps.get(0))
+            .first()
+            .and_then(|x| x.mipmaps.first())
             .unwrap_or(&def);
         let format = first
             .format
@@ -31,9 +30,9 @@ impl Texture<'_> {
             .ok_or(ddsfile::Error::UnsupportedFormat)?;
         let mipmap_levels = self
             .subtextures
-            .get(0)
+            .first()
             .and_then(|x| x.mipmaps.len().try_into().ok());
-        let caps2 = Some(Self::caps2()).filter(|_| self.subtextures.len() == 6);
+        let caps2 = Some(Self::caps2()).filter(|_| self.kind() == TextureKind::Cubemap);
         let params = NewD3dParams {
             height: first.height,
             width: first.width,
@@ -51,18 +50,18 @@ impl Texture<'_> {
         let def = Default::default();
         let first = self
             .subtextures
-            .get(0)
-            .and_then(|x| x.mipmaps.get(0))
+            .first()
+            .and_then(|x| x.mipmaps.first())
             .unwrap_or(&def);
         let format = first.format.to_dxgi_format();
         let alpha_mode = match first.format {
             DXT1 | DXT1a => AlphaMode::PreMultiplied,
             _ => AlphaMode::Straight,
         };
-        let mipmap_levels = self.subtextures.get(0).map(|x| x.mipmaps.len() as u32);
+        let mipmap_levels = self.subtextures.first().map(|x| x.mipmaps.len() as u32);
         let array_layers = self.subtextures.len().try_into().ok().filter(|&x| x > 1);
-        let caps2 = Some(Self::caps2()).filter(|_| self.subtextures.len() == 6);
-        let is_cubemap = self.subtextures.len() == 6;
+        let is_cubemap = self.kind() == TextureKind::Cubemap;
+        let caps2 = Some(Self::caps2()).filter(|_| is_cubemap);
         let params = ddsfile::NewDxgiParams {
             height: first.height,
             width: first.width,
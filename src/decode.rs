@@ -0,0 +1,783 @@
+//! Decoding mips to RGBA for every format, without the `image` crate.
+//!
+//! Block compressed formats follow the Direct3D 11 specification, BC6H being the unsigned variant.
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::io;
+
+use super::*;
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Expands an unsigned normalized value of `bits` bits to 8 bits
+fn unorm8(value: u32, bits: u32) -> u8 {
+    (value * 255 / ((1 << bits) - 1)) as u8
+}
+
+fn unorm_f32(x: f32) -> u8 {
+    (x.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+impl Mipmap<'_> {
+    /// Decodes the mip to `width * height` RGBA pixels of 8 bits per channel.
+    ///
+    /// Single channel formats are spread to gray (L8, ATI1) or to alpha (A8), and ATI2 only
+    /// fills red and green. BC6H is clamped to `0.0..=1.0`, see [`Mipmap::decode_rgba32f`].
+    ///
+    /// The smallest mips of block compressed formats may only store `width * height` pixels
+    /// worth of data, the rest of their last block is taken as zeros.
+    pub fn decode_rgba8(&self) -> io::Result<Vec<u8>> {
+        use TextureFormat::*;
+        let pixels = match self.format {
+            A8 => self.decode_pixels(|x| [0, 0, 0, x[0]]),
+            L8 => self.decode_pixels(|x| [x[0], x[0], x[0], 255]),
+            L8A8 => self.decode_pixels(|x| [x[0], x[0], x[0], x[1]]),
+            RGB8 => self.decode_pixels(|x| [x[0], x[1], x[2], 255]),
+            RGBA8 => self.decode_pixels(|x| [x[0], x[1], x[2], x[3]]),
+            RGB5 => self.decode_pixels(|x| {
+                let x = u16::from_le_bytes([x[0], x[1]]) as u32;
+                [
+                    unorm8(x >> 11, 5),
+                    unorm8(x >> 5 & 0x3F, 6),
+                    unorm8(x & 0x1F, 5),
+                    255,
+                ]
+            }),
+            RGB5A1 => self.decode_pixels(|x| {
+                let x = u16::from_le_bytes([x[0], x[1]]) as u32;
+                [
+                    unorm8(x >> 10 & 0x1F, 5),
+                    unorm8(x >> 5 & 0x1F, 5),
+                    unorm8(x & 0x1F, 5),
+                    unorm8(x >> 15, 1),
+                ]
+            }),
+            RGBA4 => self.decode_pixels(|x| {
+                let x = u16::from_le_bytes([x[0], x[1]]) as u32;
+                [
+                    unorm8(x >> 8 & 0xF, 4),
+                    unorm8(x >> 4 & 0xF, 4),
+                    unorm8(x & 0xF, 4),
+                    unorm8(x >> 12, 4),
+                ]
+            }),
+            DXT1 => self.decode_blocks(|b| decode_bc1_block(b, false, false)),
+            DXT1a => self.decode_blocks(|b| decode_bc1_block(b, true, false)),
+            DXT3 => self.decode_blocks(decode_bc2_block),
+            DXT5 => self.decode_blocks(decode_bc3_block),
+            ATI1 => self.decode_blocks(|b| decode_bc4_block(b).map(|x| [x, x, x, 255])),
+            ATI2 => self.decode_blocks(|b| {
+                let (r, g) = (decode_bc4_block(&b[..8]), decode_bc4_block(&b[8..]));
+                std::array::from_fn(|i| [r[i], g[i], 0, 255])
+            }),
+            BC7 => self.decode_blocks(decode_bc7_block),
+            BC6H => {
+                let pixels = self.decode_rgba32f()?;
+                return Ok(pixels.into_iter().map(unorm_f32).collect());
+            }
+        }?;
+        Ok(pixels.into_iter().flatten().collect())
+    }
+
+    /// Decodes the mip to `width * height` RGBA pixels of floats.
+    ///
+    /// BC6H keeps its full range, every other format is normalized to `0.0..=1.0`
+    pub fn decode_rgba32f(&self) -> io::Result<Vec<f32>> {
+        match self.format {
+            TextureFormat::BC6H => {
+                let pixels = self.decode_blocks(decode_bc6h_block)?;
+                Ok(pixels.into_iter().flatten().collect())
+            }
+            _ => {
+                let pixels = self.decode_rgba8()?;
+                Ok(pixels.into_iter().map(|x| x as f32 / 255.0).collect())
+            }
+        }
+    }
+
+    fn decode_pixels<T>(&self, f: impl Fn(&[u8]) -> [T; 4]) -> io::Result<Vec<[T; 4]>> {
+        let bpp = self.format.bytes_per_pixel().expect("uncompressed format");
+        let count = self.width as usize * self.height as usize;
+        let data = self.data.get(..count * bpp).ok_or_else(|| {
+            invalid(format!(
+                "{} has {} bytes, expected {}",
+                self,
+                self.data.len(),
+                count * bpp
+            ))
+        })?;
+        Ok(data.chunks_exact(bpp).map(f).collect())
+    }
+
+    /// Decodes every 4x4 block with `f`, cropping the blocks on the edges
+    fn decode_blocks<T, F>(&self, f: F) -> io::Result<Vec<[T; 4]>>
+    where
+        T: Copy + Default + Send,
+        F: Fn(&[u8]) -> [[T; 4]; 16] + Sync,
+    {
+        let block_size = self.format.block_size().expect("block compressed format");
+        let (w, h) = (self.width as usize, self.height as usize);
+        let (bw, bh) = (w.div_ceil(4), h.div_ceil(4));
+        let (stored, full) = ((w * h * block_size).div_ceil(16), bw * bh * block_size);
+        if self.data.len() < stored {
+            return Err(invalid(format!(
+                "{} has {} bytes, expected at least {}",
+                self,
+                self.data.len(),
+                stored
+            )));
+        }
+        if w == 0 || h == 0 {
+            return Ok(vec![]);
+        }
+        let mut data = Cow::Borrowed(self.data.get(..full).unwrap_or(&self.data));
+        if data.len() < full {
+            data.to_mut().resize(full, 0);
+        }
+
+        let mut out = vec![[T::default(); 4]; w * h];
+        let row = |(out, blocks): (&mut [[T; 4]], &[u8])| {
+            let rows = out.len() / w;
+            for (bx, block) in blocks.chunks_exact(block_size).enumerate() {
+                for (i, pixel) in f(block).iter().enumerate() {
+                    let (x, y) = (bx * 4 + i % 4, i / 4);
+                    if x < w && y < rows {
+                        out[y * w + x] = *pixel;
+                    }
+                }
+            }
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            out.par_chunks_mut(w * 4)
+                .zip(data.par_chunks(bw * block_size))
+                .for_each(row);
+        }
+        #[cfg(not(feature = "rayon"))]
+        out.chunks_mut(w * 4)
+            .zip(data.chunks(bw * block_size))
+            .for_each(row);
+        Ok(out)
+    }
+}
+
+impl Subtexture<'_> {
+    /// Decodes the image this subtexture displays, at the size of its first mip.
+    ///
+    /// A pair of ATI2 mips is taken as YUV like [`TextureKind::Yuv`]: luma and alpha are in the
+    /// full size mip, chroma in the half size one (BT.709)
+    pub fn decode_rgba8(&self) -> io::Result<Vec<u8>> {
+        let formats: Vec<_> = self.mipmaps.iter().map(|x| x.format).collect();
+        match (TextureKind::classify(1, &formats), &self.mipmaps[..]) {
+            (TextureKind::Yuv, [ya, cbcr]) => decode_yuv(ya, cbcr),
+            (_, [first, ..]) => first.decode_rgba8(),
+            (_, []) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "subtexture has no mipmaps",
+            )),
+        }
+    }
+}
+
+fn decode_yuv(ya: &Mipmap, cbcr: &Mipmap) -> io::Result<Vec<u8>> {
+    let (ya_data, cbcr_data) = (ya.decode_rgba8()?, cbcr.decode_rgba8()?);
+    let (w, h) = (ya.width as usize, ya.height as usize);
+    let (cw, ch) = (cbcr.width as usize, cbcr.height as usize);
+    if cw == 0 || ch == 0 {
+        return Err(invalid(format!("empty {}x{} chroma mip", cw, ch)));
+    }
+    if ya_data.len() != w * h * 4 || cbcr_data.len() != cw * ch * 4 {
+        return Err(invalid(
+            "luma and chroma mips don't match their size".to_string(),
+        ));
+    }
+    let mut out = Vec::with_capacity(w * h * 4);
+    for y in 0..h {
+        for x in 0..w {
+            let i = (y * w + x) * 4;
+            let j = ((y / 2).min(ch - 1) * cw + (x / 2).min(cw - 1)) * 4;
+            let luma = ya_data[i] as f32 / 255.0;
+            let cb = cbcr_data[j] as f32 / 255.0 - 0.5;
+            let cr = cbcr_data[j + 1] as f32 / 255.0 - 0.5;
+            let r = luma + 1.5748 * cr;
+            let g = luma - 0.1873 * cb - 0.4681 * cr;
+            let b = luma + 1.8556 * cb;
+            out.extend([unorm_f32(r), unorm_f32(g), unorm_f32(b), ya_data[i + 1]]);
+        }
+    }
+    Ok(out)
+}
+
+/// Reads the fields of a 128 bit block, least significant bit first
+struct Bits(u128);
+
+impl Bits {
+    fn new(block: &[u8]) -> Self {
+        Self(u128::from_le_bytes(
+            block.try_into().expect("16 byte block"),
+        ))
+    }
+
+    fn read(&mut self, bits: u32) -> u32 {
+        let value = (self.0 & ((1 << bits) - 1)) as u32;
+        self.0 >>= bits;
+        value
+    }
+}
+
+/// Color block of BC1, BC2 and BC3. Only BC1 has the 3 color mode, with transparent black if `alpha`
+fn decode_bc1_block(block: &[u8], alpha: bool, four_colors: bool) -> [[u8; 4]; 16] {
+    let rgb565 = |x: u16| {
+        let x = x as u32;
+        [
+            unorm8(x >> 11, 5),
+            unorm8(x >> 5 & 0x3F, 6),
+            unorm8(x & 0x1F, 5),
+            255,
+        ]
+    };
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (e0, e1) = (rgb565(c0), rgb565(c1));
+    let mix = |a: u8, b: u8, wa: u16, wb: u16| {
+        ((a as u16 * wa + b as u16 * wb + (wa + wb) / 2) / (wa + wb)) as u8
+    };
+    let mut colors = [e0, e1, [0; 4], [0, 0, 0, if alpha { 0 } else { 255 }]];
+    for c in 0..3 {
+        if c0 > c1 || four_colors {
+            colors[2][c] = mix(e0[c], e1[c], 2, 1);
+            colors[3][c] = mix(e0[c], e1[c], 1, 2);
+        } else {
+            colors[2][c] = mix(e0[c], e1[c], 1, 1);
+        }
+    }
+    colors[2][3] = 255;
+    if c0 > c1 || four_colors {
+        colors[3][3] = 255;
+    }
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|i| colors[(indices >> (2 * i) & 3) as usize])
+}
+
+fn decode_bc2_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+    let mut pixels = decode_bc1_block(&block[8..], false, true);
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        pixel[3] = (alpha >> (4 * i) & 0xF) as u8 * 0x11;
+    }
+    pixels
+}
+
+fn decode_bc3_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let alpha = decode_bc4_block(&block[..8]);
+    let mut pixels = decode_bc1_block(&block[8..], false, true);
+    for (pixel, alpha) in pixels.iter_mut().zip(alpha) {
+        pixel[3] = alpha;
+    }
+    pixels
+}
+
+/// A single channel block, also the alpha of BC3 and both channels of BC5
+fn decode_bc4_block(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let palette: [u8; 8] = std::array::from_fn(|i| {
+        let i = i as u32;
+        (match i {
+            0 => a0,
+            1 => a1,
+            _ if a0 > a1 => ((8 - i) * a0 + (i - 1) * a1) / 7,
+            6 => 0,
+            7 => 255,
+            _ => ((6 - i) * a0 + (i - 1) * a1) / 5,
+        }) as u8
+    });
+    let mut raw = [0; 8];
+    raw[..6].copy_from_slice(&block[2..8]);
+    let bits = u64::from_le_bytes(raw);
+    std::array::from_fn(|i| palette[(bits >> (3 * i) & 7) as usize])
+}
+
+/// Subset of each pixel in the BC6H and BC7 partitions of 2 subsets, one bit per pixel
+const PARTITIONS2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80, 0xC800, 0xFFEC, 0xFE80, 0xE800,
+    0xFFE8, 0xFF00, 0xFFF0, 0xF000, 0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C, 0xAAAA, 0xF0F0, 0x5A5A, 0x33CC,
+    0x3C3C, 0x55AA, 0x9696, 0xA55A, 0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C, 0x9336, 0x9CC6, 0x817E, 0xE718,
+    0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// Subset of each pixel in the BC7 partitions of 3 subsets, two bits per pixel
+const PARTITIONS3: [u32; 64] = [
+    0xAA685050, 0x6A5A5040, 0x5A5A4200, 0x5450A0A8, 0xA5A50000, 0xA0A05050, 0x5555A0A0, 0x5A5A5050,
+    0xAA550000, 0xAA555500, 0xAAAA5500, 0x90909090, 0x94949494, 0xA4A4A4A4, 0xA9A59450, 0x2A0A4250,
+    0xA5945040, 0x0A425054, 0xA5A5A500, 0x55A0A0A0, 0xA8A85454, 0x6A6A4040, 0xA4A45000, 0x1A1A0500,
+    0x0050A4A4, 0xAAA59090, 0x14696914, 0x69691400, 0xA08585A0, 0xAA821414, 0x50A4A450, 0x6A5A0200,
+    0xA9A58000, 0x5090A0A8, 0xA8A09050, 0x24242424, 0x00AA5500, 0x24924924, 0x24499224, 0x50A50A50,
+    0x500AA550, 0xAAAA4444, 0x66660000, 0xA5A0A5A0, 0x50A050A0, 0x69286928, 0x44AAAA44, 0x66666600,
+    0xAA444444, 0x54A854A8, 0x95809580, 0x96969600, 0xA85454A8, 0x80959580, 0xAA141414, 0x96960000,
+    0xAAAA1414, 0xA05050A0, 0xA0A5A5A0, 0x96000000, 0x40804080, 0xA9A8A9A8, 0xAAAAAA44, 0x2A4A5254,
+];
+
+/// Pixel whose index drops its top bit, for the second subset of [`PARTITIONS2`]
+const ANCHORS2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Anchors of the second and third subsets of [`PARTITIONS3`]
+const ANCHORS3: [[u8; 64]; 2] = [
+    [
+        3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3, 3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6,
+        8, 5, 15, 15, 8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15, 3, 15, 5, 5, 5, 8,
+        5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+    ],
+    [
+        15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8, 15, 8, 15, 3, 15, 8, 15, 8, 3,
+        15, 6, 10, 15, 15, 10, 8, 15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8, 15, 3, 15,
+        15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+    ],
+];
+
+/// Subset of pixel `i` and whether it is the anchor of that subset
+fn subset(subsets: usize, partition: usize, i: usize) -> (usize, bool) {
+    let subset = match subsets {
+        1 => 0,
+        2 => (PARTITIONS2[partition] >> i & 1) as usize,
+        _ => (PARTITIONS3[partition] >> (2 * i) & 3) as usize,
+    };
+    let anchor = match (subsets, subset) {
+        (_, 0) => 0,
+        (2, _) => ANCHORS2[partition],
+        (_, s) => ANCHORS3[s - 1][partition],
+    };
+    (subset, i == anchor as usize)
+}
+
+fn weight(bits: u32, index: u32) -> u32 {
+    const WEIGHTS2: [u32; 4] = [0, 21, 43, 64];
+    const WEIGHTS3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+    const WEIGHTS4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+    match bits {
+        2 => WEIGHTS2[index as usize],
+        3 => WEIGHTS3[index as usize],
+        _ => WEIGHTS4[index as usize],
+    }
+}
+
+fn interpolate(e0: u32, e1: u32, weight: u32) -> u32 {
+    ((64 - weight) * e0 + weight * e1 + 32) >> 6
+}
+
+/// Reads the indices of every pixel, anchors having one bit less
+fn read_indices(bits: &mut Bits, index_bits: u32, subsets: usize, partition: usize) -> [u32; 16] {
+    std::array::from_fn(|i| {
+        let anchor = subset(subsets, partition, i).1;
+        bits.read(index_bits - anchor as u32)
+    })
+}
+
+/// How a BC7 mode is laid out, `(subsets, partition bits, rotation bits, index selection bits,
+/// color bits, alpha bits, p-bit per endpoint, p-bit per subset, index bits, secondary index bits)`
+type Bc7Mode = (usize, u32, u32, u32, u32, u32, bool, bool, u32, u32);
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    (3, 4, 0, 0, 4, 0, true, false, 3, 0),
+    (2, 6, 0, 0, 6, 0, false, true, 3, 0),
+    (3, 6, 0, 0, 5, 0, false, false, 2, 0),
+    (2, 6, 0, 0, 7, 0, true, false, 2, 0),
+    (1, 0, 2, 1, 5, 6, false, false, 2, 3),
+    (1, 0, 2, 0, 7, 8, false, false, 2, 2),
+    (1, 0, 0, 0, 7, 7, true, false, 4, 0),
+    (2, 6, 0, 0, 5, 5, true, false, 2, 0),
+];
+
+fn decode_bc7_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut bits = Bits::new(block);
+    let mode = match (0..8).find(|_| bits.read(1) == 1) {
+        Some(mode) => mode,
+        // Reserved, decodes to transparent black
+        None => return [[0; 4]; 16],
+    };
+    let (
+        subsets,
+        partition_bits,
+        rotation_bits,
+        selection_bits,
+        color_bits,
+        alpha_bits,
+        pbits,
+        shared_pbits,
+        index_bits,
+        index_bits2,
+    ) = BC7_MODES[mode];
+    let partition = bits.read(partition_bits) as usize;
+    let rotation = bits.read(rotation_bits);
+    let selection = bits.read(selection_bits);
+
+    let mut endpoints = [[0u32; 4]; 6];
+    let endpoints = &mut endpoints[..subsets * 2];
+    for c in 0..4 {
+        let bits_c = if c < 3 { color_bits } else { alpha_bits };
+        for e in endpoints.iter_mut() {
+            e[c] = bits.read(bits_c);
+        }
+    }
+    let mut p = [0; 6];
+    if pbits {
+        p.iter_mut()
+            .take(subsets * 2)
+            .for_each(|p| *p = bits.read(1));
+    } else if shared_pbits {
+        for s in 0..subsets {
+            let bit = bits.read(1);
+            p[2 * s] = bit;
+            p[2 * s + 1] = bit;
+        }
+    }
+    for (e, &p) in endpoints.iter_mut().zip(&p) {
+        for (c, value) in e.iter_mut().enumerate() {
+            let mut n = if c < 3 { color_bits } else { alpha_bits };
+            if n == 0 {
+                *value = 255;
+                continue;
+            }
+            if pbits || shared_pbits {
+                *value = *value << 1 | p;
+                n += 1;
+            }
+            *value = *value << (8 - n) | *value >> (2 * n - 8);
+        }
+    }
+
+    let indices = read_indices(&mut bits, index_bits, subsets, partition);
+    let indices2 = match index_bits2 {
+        0 => indices,
+        n => read_indices(&mut bits, n, 1, 0),
+    };
+    let (color, alpha) = match (index_bits2, selection) {
+        (0, _) => ((&indices, index_bits), (&indices, index_bits)),
+        (n, 0) => ((&indices, index_bits), (&indices2, n)),
+        (n, _) => ((&indices2, n), (&indices, index_bits)),
+    };
+    std::array::from_fn(|i| {
+        let s = subset(subsets, partition, i).0;
+        let (e0, e1) = (endpoints[2 * s], endpoints[2 * s + 1]);
+        let mut pixel: [u8; 4] = std::array::from_fn(|c| {
+            let (indices, bits) = if c < 3 { color } else { alpha };
+            interpolate(e0[c], e1[c], weight(bits, indices[i])) as u8
+        });
+        if rotation > 0 {
+            pixel.swap(rotation as usize - 1, 3);
+        }
+        pixel
+    })
+}
+
+// Endpoints of BC6H, w and x being the first subset and y and z the second one
+const RW: usize = 0;
+const GW: usize = 1;
+const BW: usize = 2;
+const RX: usize = 3;
+const GX: usize = 4;
+const BX: usize = 5;
+const RY: usize = 6;
+const GY: usize = 7;
+const BY: usize = 8;
+const RZ: usize = 9;
+const GZ: usize = 10;
+const BZ: usize = 11;
+
+/// Where a BC6H header stores endpoint bits, `(endpoint, first bit, bit count)` in order
+type Fields = &'static [(usize, u32, u32)];
+
+/// Layout, endpoint bits and delta bits of each BC6H mode, `None` for reserved modes
+fn bc6h_mode(mode: u32) -> Option<(Fields, u32, [u32; 3])> {
+    #[rustfmt::skip]
+    let mode = match mode {
+        0b00 => (&[(GY, 4, 1), (BY, 4, 1), (BZ, 4, 1), (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1)][..], 10, [5, 5, 5]),
+        0b01 => (&[(GY, 5, 1), (GZ, 4, 1), (GZ, 5, 1), (RW, 0, 7), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 7), (BY, 5, 1), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 7), (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6)][..], 7, [6, 6, 6]),
+        0b00010 => (&[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (RW, 10, 1), (GY, 0, 4), (GX, 0, 4), (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1)][..], 11, [5, 4, 4]),
+        0b00110 => (&[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (GW, 10, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 4), (BZ, 0, 1), (BZ, 2, 1), (RZ, 0, 4), (GY, 4, 1), (BZ, 3, 1)][..], 11, [4, 5, 4]),
+        0b01010 => (&[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (BY, 4, 1), (GY, 0, 4), (GX, 0, 4), (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BW, 10, 1), (BY, 0, 4), (RY, 0, 4), (BZ, 1, 1), (BZ, 2, 1), (RZ, 0, 4), (BZ, 4, 1), (BZ, 3, 1)][..], 11, [4, 4, 5]),
+        0b01110 => (&[(RW, 0, 9), (BY, 4, 1), (GW, 0, 9), (GY, 4, 1), (BW, 0, 9), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1)][..], 9, [5, 5, 5]),
+        0b10010 => (&[(RW, 0, 8), (GZ, 4, 1), (BY, 4, 1), (GW, 0, 8), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 8), (BZ, 3, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6)][..], 8, [6, 5, 5]),
+        0b10110 => (&[(RW, 0, 8), (BZ, 0, 1), (BY, 4, 1), (GW, 0, 8), (GY, 5, 1), (GY, 4, 1), (BW, 0, 8), (GZ, 5, 1), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1)][..], 8, [5, 6, 5]),
+        0b11010 => (&[(RW, 0, 8), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 8), (BY, 5, 1), (GY, 4, 1), (BW, 0, 8), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1)][..], 8, [5, 5, 6]),
+        0b11110 => (&[(RW, 0, 6), (GZ, 4, 1), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 6), (GY, 5, 1), (BY, 5, 1), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 6), (GZ, 5, 1), (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6)][..], 6, [6, 6, 6]),
+        0b00011 => (&[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 10), (GX, 0, 10), (BX, 0, 10)][..], 10, [10, 10, 10]),
+        0b00111 => (&[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 9), (RW, 10, 1), (GX, 0, 9), (GW, 10, 1), (BX, 0, 9), (BW, 10, 1)][..], 11, [9, 9, 9]),
+        // The high bits of the base are stored reversed
+        0b01011 => (&[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 8), (RW, 11, 1), (RW, 10, 1), (GX, 0, 8), (GW, 11, 1), (GW, 10, 1), (BX, 0, 8), (BW, 11, 1), (BW, 10, 1)][..], 12, [8, 8, 8]),
+        0b01111 => (&[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 15, 1), (RW, 14, 1), (RW, 13, 1), (RW, 12, 1), (RW, 11, 1), (RW, 10, 1), (GX, 0, 4), (GW, 15, 1), (GW, 14, 1), (GW, 13, 1), (GW, 12, 1), (GW, 11, 1), (GW, 10, 1), (BX, 0, 4), (BW, 15, 1), (BW, 14, 1), (BW, 13, 1), (BW, 12, 1), (BW, 11, 1), (BW, 10, 1)][..], 16, [4, 4, 4]),
+        _ => return None,
+    };
+    Some(mode)
+}
+
+fn sign_extend(value: u32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    (value << shift) as i32 >> shift
+}
+
+/// Widens an unsigned endpoint to 16 bits, keeping both ends of the range exact
+fn unquantize_bc6h(value: u32, bits: u32) -> u32 {
+    match value {
+        _ if bits >= 15 => value,
+        0 => 0,
+        _ if value == (1 << bits) - 1 => 0xFFFF,
+        _ => ((value << 16) + 0x8000) >> bits,
+    }
+}
+
+/// Scales an interpolated value to the half float it stands for
+fn finish_bc6h(value: u32) -> f32 {
+    half_to_f32(((value * 31) >> 6) as u16)
+}
+
+/// Converts a positive half float, which is all unsigned BC6H produces
+fn half_to_f32(half: u16) -> f32 {
+    let (exponent, mantissa) = ((half >> 10 & 0x1F) as u32, (half & 0x3FF) as u32);
+    match exponent {
+        0 => mantissa as f32 / (1 << 24) as f32,
+        0x1F => f32::from_bits(0x7F80_0000 | mantissa << 13),
+        _ => f32::from_bits((exponent + 112) << 23 | mantissa << 13),
+    }
+}
+
+fn decode_bc6h_block(block: &[u8]) -> [[f32; 4]; 16] {
+    let mut bits = Bits::new(block);
+    let mode = match bits.read(2) {
+        mode @ (0 | 1) => mode,
+        mode => mode | bits.read(3) << 2,
+    };
+    let (fields, endpoint_bits, delta_bits) = match bc6h_mode(mode) {
+        Some(mode) => mode,
+        None => return [[0.0, 0.0, 0.0, 1.0]; 16],
+    };
+    let mut endpoints = [0u32; 12];
+    for &(endpoint, shift, count) in fields {
+        endpoints[endpoint] |= bits.read(count) << shift;
+    }
+    // Modes with a single subset have a 3 bit marker after the mode bits
+    let subsets = if mode & 3 == 3 { 1 } else { 2 };
+    let transformed = !matches!(mode, 0b11110 | 0b00011);
+    let mask = (1 << endpoint_bits) - 1;
+    if transformed {
+        for i in 3..subsets * 6 {
+            let (base, delta) = (
+                endpoints[i % 3],
+                sign_extend(endpoints[i], delta_bits[i % 3]),
+            );
+            endpoints[i] = (base as i32).wrapping_add(delta) as u32 & mask;
+        }
+    }
+    let endpoints = endpoints.map(|x| unquantize_bc6h(x, endpoint_bits));
+
+    let partition = match subsets {
+        1 => 0,
+        _ => bits.read(5) as usize,
+    };
+    let index_bits = if subsets == 1 { 4 } else { 3 };
+    let indices = read_indices(&mut bits, index_bits, subsets, partition);
+    std::array::from_fn(|i| {
+        let s = subset(subsets, partition, i).0;
+        let w = weight(index_bits, indices[i]);
+        let mut pixel = [1.0; 4];
+        for (c, value) in pixel.iter_mut().take(3).enumerate() {
+            let (e0, e1) = (endpoints[s * 6 + c], endpoints[s * 6 + 3 + c]);
+            *value = finish_bc6h(interpolate(e0, e1, w));
+        }
+        pixel
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packs `(value, bits)` fields into a block, least significant bit first
+    fn pack(fields: &[(u32, u32)]) -> [u8; 16] {
+        let mut block = 0u128;
+        let mut at = 0;
+        for &(value, bits) in fields {
+            block |= (value as u128) << at;
+            at += bits;
+        }
+        assert_eq!(at, 128);
+        block.to_le_bytes()
+    }
+
+    fn mip(format: TextureFormat, width: u32, height: u32, data: &[u8]) -> Mipmap<'static> {
+        Mipmap {
+            format,
+            width,
+            height,
+            data: data.to_vec().into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decode_uncompressed() {
+        use TextureFormat::*;
+        let decode = |format, data: &[u8]| mip(format, 1, 1, data).decode_rgba8().unwrap();
+        assert_eq!(decode(A8, &[7]), [0, 0, 0, 7]);
+        assert_eq!(decode(L8A8, &[7, 9]), [7, 7, 7, 9]);
+        assert_eq!(decode(RGB8, &[1, 2, 3]), [1, 2, 3, 255]);
+        assert_eq!(decode(RGB5, &0xF81Fu16.to_le_bytes()), [255, 0, 255, 255]);
+        assert_eq!(decode(RGB5A1, &0x83E0u16.to_le_bytes()), [0, 255, 0, 255]);
+        assert_eq!(decode(RGBA4, &0x0F00u16.to_le_bytes()), [255, 0, 0, 0]);
+        assert_eq!(
+            mip(RGB8, 2, 1, &[0; 5]).decode_rgba8().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn decode_atlas() {
+        let input = include_bytes!("../assets/mikitm001_tex.txp");
+        let (_, atlas) = TextureAtlas::parse(input).unwrap();
+        let mips = atlas
+            .0
+            .iter()
+            .flat_map(|x| &x.subtextures)
+            .flat_map(|x| &x.mipmaps);
+        // Including the truncated smallest mips
+        for mip in mips {
+            let rgba = mip.decode_rgba8().unwrap();
+            assert_eq!(rgba.len(), (mip.width * mip.height * 4) as usize, "{}", mip);
+        }
+
+        let input = include_bytes!("../assets/spr_sel_pv616.bin");
+        let (_, atlas) = TextureAtlas::parse(input).unwrap();
+        let yuv = atlas.0[0].subtextures[0].decode_rgba8().unwrap();
+        assert_eq!(yuv.len(), 1024 * 512 * 4);
+        let mip = &atlas.0[1].subtextures[0].mipmaps[0];
+        let floats = mip.decode_rgba32f().unwrap();
+        let rgba = mip.decode_rgba8().unwrap();
+        assert!(floats.iter().zip(&rgba).all(|(&f, &x)| unorm_f32(f) == x));
+
+        let mut yuv = atlas.0[0].subtextures[0].clone();
+        yuv.mipmaps[1].width = 0;
+        yuv.mipmaps[1].height = 0;
+        yuv.mipmaps[1].data = Cow::Borrowed(&[]);
+        let err = yuv.decode_rgba8().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn decode_bc1() {
+        let white = mip(TextureFormat::DXT1, 2, 2, &[0xFF, 0xFF]);
+        assert_eq!(white.decode_rgba8().unwrap(), [255; 16]);
+        // Black in the 3 color mode is only transparent for DXT1a
+        let block = [0, 0, 0xFF, 0xFF, 0xFF, 0xAA, 0, 0];
+        let dxt1 = mip(TextureFormat::DXT1, 4, 4, &block)
+            .decode_rgba8()
+            .unwrap();
+        assert_eq!(dxt1[..8], [0, 0, 0, 255, 0, 0, 0, 255]);
+        let dxt1a = mip(TextureFormat::DXT1a, 4, 4, &block)
+            .decode_rgba8()
+            .unwrap();
+        assert_eq!(dxt1a[..8], [0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(dxt1a[16..20], [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn partitions() {
+        for partition in 0..64 {
+            for subsets in 2..=3 {
+                // Every subset has exactly one anchor
+                let anchors = (0..16).filter(|&i| subset(subsets, partition, i).1);
+                let mut anchored: Vec<_> =
+                    anchors.map(|i| subset(subsets, partition, i).0).collect();
+                anchored.sort();
+                assert_eq!(anchored, (0..subsets).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn decode_bc7() {
+        // Mode 6: RGBA endpoints of 7 bits with a p-bit each, 4 bit indices
+        let mut fields = vec![(1 << 6, 7)];
+        fields.extend([
+            (0, 7),
+            (127, 7),
+            (0, 7),
+            (64, 7),
+            (0, 7),
+            (0, 7),
+            (127, 7),
+            (127, 7),
+        ]);
+        fields.extend([(0, 1), (1, 1)]);
+        fields.push((0, 3));
+        fields.extend((1..16).map(|i| (i, 4)));
+        let block = pack(&fields);
+        let pixels = decode_bc7_block(&block);
+        assert_eq!(pixels[0], [0, 0, 0, 254]);
+        assert_eq!(pixels[15], [255, 129, 1, 255]);
+        assert_eq!(pixels[8], [135, 69, 1, 255]);
+
+        // Mode 4 with its alpha rotated into red
+        let mut fields = vec![(1 << 4, 5), (1, 2), (0, 1)];
+        fields.extend([(31, 5), (31, 5), (0, 5), (31, 5), (0, 5), (0, 5)]);
+        fields.extend([(0, 6), (63, 6)]);
+        fields.extend(std::iter::once((0, 1)).chain((1..16).map(|_| (3, 2))));
+        fields.extend(std::iter::once((0, 2)).chain((1..16).map(|_| (0, 3))));
+        let pixels = decode_bc7_block(&pack(&fields));
+        assert_eq!(pixels[0], [0, 0, 0, 255]);
+        assert_eq!(pixels[1], [0, 255, 0, 255]);
+
+        assert_eq!(decode_bc7_block(&[0; 16]), [[0; 4]; 16]);
+    }
+
+    #[test]
+    fn decode_bc6h() {
+        // Mode 11: a single subset of two 10 bit endpoints, 4 bit indices
+        let mut fields = vec![(0b00011, 5), (0, 10), (0, 10), (0, 10)];
+        fields.extend([(1023, 10), (0x200, 10), (0, 10)]);
+        fields.push((0, 3));
+        fields.extend((1..16).map(|_| (15, 4)));
+        let pixels = decode_bc6h_block(&pack(&fields));
+        assert_eq!(pixels[0], [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pixels[1], [65504.0, finish_bc6h(0x8020), 0.0, 1.0]);
+        assert_eq!(half_to_f32(0x3C00), 1.0);
+        assert_eq!(half_to_f32(0x0001), 2f32.powi(-24));
+
+        // Mode 1: the second endpoint of each subset is a delta of the first one
+        let mut fields = vec![(0, 2), (0, 1), (0, 1), (0, 1), (100, 10), (0, 10), (0, 10)];
+        fields.extend([
+            (0x1F, 5),
+            (0, 1),
+            (0, 4),
+            (0, 5),
+            (0, 1),
+            (0, 4),
+            (0, 5),
+            (0, 1),
+        ]);
+        fields.extend([(0, 4), (1, 5), (0, 1), (0, 5), (0, 1)]);
+        // Partition 13 puts the bottom half in the second subset, anchored at the last pixel
+        fields.push((13, 5));
+        fields.extend((0..16).map(|i| if i == 0 || i == 15 { (3, 2) } else { (7, 3) }));
+        let pixels = decode_bc6h_block(&pack(&fields));
+        let red = |e0, e1, w| {
+            finish_bc6h(interpolate(
+                unquantize_bc6h(e0, 10),
+                unquantize_bc6h(e1, 10),
+                w,
+            ))
+        };
+        assert_eq!(pixels[0][0], red(100, 99, 27));
+        assert_eq!(pixels[1][0], red(100, 99, 64));
+        assert_eq!(pixels[8][0], red(101, 100, 64));
+        assert_eq!(pixels[15][0], red(101, 100, 27));
+        assert!(pixels[15][0] > pixels[8][0]);
+    }
+}
@@ -0,0 +1,404 @@
+//! Reading and writing of SEGA's FARC archives, which is how most `_tex.bin`s are shipped.
+//!
+//! Three variants exist, all big endian:
+//! - `FArc` :: stored entries
+//! - `FArC` :: gzip compressed entries
+//! - `FARC` :: entries that are optionally gzip compressed and/or AES encrypted
+use std::borrow::Cow;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, KeyInit};
+use aes::Aes128;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take, take_until};
+use nom::combinator::{map, map_res};
+use nom::number::complete::be_u32;
+use nom::IResult;
+use tracing::{debug, trace};
+
+/// AES-128 key used by the encrypted `FARC`s of DT, F and F2nd
+const KEY: &[u8; 16] = b"project_diva.bin";
+
+const FLAG_COMPRESSED: u32 = 1 << 1;
+const FLAG_ENCRYPTED: u32 = 1 << 2;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Signature {
+    /// `FArc`, uncompressed
+    Stored,
+    /// `FArC`, gzip compressed
+    Compressed,
+    /// `FARC`, compression and encryption depend on the header flags
+    Extended,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Farc<'a> {
+    pub signature: Signature,
+    pub alignment: u32,
+    pub entries: Vec<Entry<'a>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entry<'a> {
+    pub name: String,
+    pub compressed: bool,
+    pub encrypted: bool,
+    /// Size of the entry once compression has been undone
+    pub size: u32,
+    /// Size of the entry as stored in the archive, before encryption padding
+    pub stored_size: u32,
+    /// The data as stored in the archive, see [`Entry::data`] for the usable contents
+    pub raw: Cow<'a, [u8]>,
+}
+
+fn name(i: &[u8]) -> IResult<&[u8], String> {
+    let (i, name) = map_res(take_until(&b"\0"[..]), std::str::from_utf8)(i)?;
+    let (i, _) = take(1usize)(i)?;
+    Ok((i, name.to_string()))
+}
+
+impl<'a> Farc<'a> {
+    #[tracing::instrument(name = "farc", skip(i0))]
+    pub fn parse(i0: &'a [u8]) -> IResult<&'a [u8], Farc<'a>> {
+        use Signature::*;
+        let (i, signature) = alt((
+            map(tag(b"FArc"), |_| Stored),
+            map(tag(b"FArC"), |_| Compressed),
+            map(tag(b"FARC"), |_| Extended),
+        ))(i0)?;
+        let (i, header_size) = be_u32(i)?;
+        let (mut i, flags) = match signature {
+            Stored => (i, 0),
+            Compressed => (i, FLAG_COMPRESSED),
+            Extended => {
+                let (i, flags) = be_u32(i)?;
+                let (i, _padding) = be_u32(i)?;
+                (i, flags)
+            }
+        };
+        let (i1, alignment) = be_u32(i)?;
+        i = i1;
+        let encrypted = flags & FLAG_ENCRYPTED != 0;
+        let compressed = flags & FLAG_COMPRESSED != 0;
+        if signature == Extended {
+            // Future Tone encrypts the rest of the header with a different scheme,
+            // it is recognizable by its bogus alignment
+            if encrypted && !alignment.is_power_of_two() {
+                return Err(nom::Err::Failure(nom::error::Error::new(
+                    i,
+                    nom::error::ErrorKind::Verify,
+                )));
+            }
+            // Newer `FARC`s store a format marker and the entry count before the entries,
+            // names can never start with a nul so this is unambiguous
+            if i.first() == Some(&0) {
+                let (i1, _) = be_u32(i)?;
+                let (i1, _count) = be_u32(i1)?;
+                i = i1;
+            }
+        }
+        debug!(?signature, header_size, flags, alignment);
+
+        let header_end = header_size as usize + 8;
+        let mut entries = vec![];
+        while i0.len() - i.len() < header_end {
+            let (i1, name) = name(i)?;
+            let (i1, offset) = be_u32(i1)?;
+            let (i1, stored_size) = be_u32(i1)?;
+            let (i1, size) = match signature {
+                Stored => (i1, stored_size),
+                _ => be_u32(i1)?,
+            };
+            i = i1;
+            let len = if encrypted {
+                (stored_size as usize + 15) & !15
+            } else {
+                stored_size as usize
+            };
+            let (rest, _) = take(offset as usize)(i0)?;
+            let (_, raw) = take(len)(rest)?;
+            trace!(name, offset, stored_size, size);
+            entries.push(Entry {
+                name,
+                compressed,
+                encrypted,
+                size,
+                stored_size,
+                raw: raw.into(),
+            });
+        }
+        Ok((
+            i,
+            Self {
+                signature,
+                alignment,
+                entries,
+            },
+        ))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Entry<'a>> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// Creates an empty archive, only `Stored` and `Compressed` can be written
+    pub fn new(signature: Signature) -> Self {
+        Self {
+            signature,
+            alignment: 0x10,
+            entries: vec![],
+        }
+    }
+
+    /// Adds a file to the archive, compressing it if the archive is `FArC`
+    ///
+    /// ```
+    /// # use txp::farc::{Farc, Signature};
+    /// # let atlas = txp::TextureAtlas(vec![]);
+    /// let mut farc = Farc::new(Signature::Compressed);
+    /// farc.push("mikitm001_tex.bin", atlas.try_to_bytes()?)?;
+    /// let mut out = vec![];
+    /// farc.write(&mut out)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn push<N, D>(&mut self, name: N, data: D) -> io::Result<()>
+    where
+        N: Into<String>,
+        D: Into<Cow<'a, [u8]>>,
+    {
+        let compressed = self.signature == Signature::Compressed;
+        let entry = Entry::new(name.into(), data.into(), compressed)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "farc", skip(self, w))]
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let magic: &[u8] = match self.signature {
+            Signature::Stored => b"FArc",
+            Signature::Compressed => b"FArC",
+            Signature::Extended => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "writing `FARC` archives is not supported",
+                ))
+            }
+        };
+        let sizes = if self.signature == Signature::Compressed {
+            8
+        } else {
+            4
+        };
+        let header_size = 4 + self
+            .entries
+            .iter()
+            .map(|e| e.name.len() + 1 + 4 + sizes)
+            .sum::<usize>();
+        let align = |x: usize| {
+            let alignment = self.alignment.max(1) as usize;
+            x.div_ceil(alignment) * alignment
+        };
+        debug!(?self.signature, header_size, self.alignment);
+
+        w.write_all(magic)?;
+        w.write_all(&(header_size as u32).to_be_bytes())?;
+        w.write_all(&self.alignment.to_be_bytes())?;
+        let mut offset = align(header_size + 8);
+        for entry in &self.entries {
+            w.write_all(entry.name.as_bytes())?;
+            w.write_all(&[0])?;
+            w.write_all(&(offset as u32).to_be_bytes())?;
+            w.write_all(&entry.stored_size.to_be_bytes())?;
+            if self.signature == Signature::Compressed {
+                w.write_all(&entry.size.to_be_bytes())?;
+            }
+            offset = align(offset + entry.raw.len());
+        }
+        let mut pos = header_size + 8;
+        for entry in &self.entries {
+            w.write_all(&vec![0; align(pos) - pos])?;
+            w.write_all(&entry.raw)?;
+            pos = align(pos) + entry.raw.len();
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Entry<'a> {
+    pub fn new(name: String, data: Cow<'a, [u8]>, compressed: bool) -> io::Result<Self> {
+        let size = data.len() as u32;
+        let raw = if compressed {
+            let mut enc = GzEncoder::new(vec![], flate2::Compression::default());
+            enc.write_all(&data)?;
+            enc.finish()?.into()
+        } else {
+            data
+        };
+        Ok(Self {
+            name,
+            compressed,
+            encrypted: false,
+            size,
+            stored_size: raw.len() as u32,
+            raw,
+        })
+    }
+
+    /// Decrypts and decompresses the entry
+    #[tracing::instrument(skip(self), fields(name = %self.name))]
+    pub fn data(&self) -> io::Result<Cow<'_, [u8]>> {
+        let mut data = self.raw.clone();
+        if self.encrypted {
+            let cipher = Aes128::new(GenericArray::from_slice(KEY));
+            let buf = data.to_mut();
+            for block in buf.chunks_exact_mut(16) {
+                cipher.decrypt_block(GenericArray::from_mut_slice(block));
+            }
+            buf.truncate(self.stored_size as usize);
+        }
+        if self.compressed && self.stored_size != self.size {
+            let mut out = Vec::with_capacity(self.size as usize);
+            GzDecoder::new(&data[..]).read_to_end(&mut out)?;
+            data = out.into();
+        }
+        Ok(data)
+    }
+}
+
+/// Reads a file from disk, paths of the form `archive.farc!/name_tex.bin`
+/// are resolved to the contents of the entry `name_tex.bin` inside `archive.farc`
+///
+/// ```no_run
+/// let data = txp::farc::read("mikitm001.farc!/mikitm001_tex.bin")?;
+/// let (_, atlas) = txp::TextureAtlas::parse(&data).unwrap();
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let path = path.as_ref();
+    let (archive, name) = match path.to_str().and_then(|x| x.split_once("!/")) {
+        Some(split) => split,
+        None => return std::fs::read(path),
+    };
+    let input = std::fs::read(archive)?;
+    let (_, farc) = Farc::parse(&input)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let entry = farc.get(name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("`{}` is not in `{}`", name, archive),
+        )
+    })?;
+    entry.data().map(Cow::into_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextureAtlas;
+
+    const INPUT: &[u8] = include_bytes!("../assets/mikitm001_tex.txp");
+    const NAME: &str = "mikitm001_tex.bin";
+
+    fn header(magic: &[u8], fields: &[u32], entry: &[u32]) -> Vec<u8> {
+        let mut header = vec![];
+        for x in fields {
+            header.extend(x.to_be_bytes());
+        }
+        header.extend(NAME.as_bytes());
+        header.push(0);
+        let size = header.len() + (entry.len() + 1) * 4;
+        // entries start right after the header
+        let offset = (size + 8) as u32;
+        header.extend(offset.to_be_bytes());
+        for x in entry {
+            header.extend(x.to_be_bytes());
+        }
+        let mut out = magic.to_vec();
+        out.extend((size as u32).to_be_bytes());
+        out.extend(header);
+        out
+    }
+
+    #[test]
+    fn read_stored() {
+        let mut input = header(b"FArc", &[0x20], &[INPUT.len() as u32]);
+        input.extend(INPUT);
+        let (_, farc) = Farc::parse(&input).unwrap();
+        assert_eq!(farc.signature, Signature::Stored);
+        let data = farc.get(NAME).unwrap().data().unwrap();
+        let (_, atlas) = TextureAtlas::parse(&data).unwrap();
+        assert_eq!(atlas.0.len(), 18);
+    }
+
+    #[test]
+    fn read_compressed() {
+        let mut enc = GzEncoder::new(vec![], flate2::Compression::fast());
+        enc.write_all(INPUT).unwrap();
+        let gz = enc.finish().unwrap();
+        let mut input = header(b"FArC", &[0x20], &[gz.len() as u32, INPUT.len() as u32]);
+        input.extend(&gz);
+        let (_, farc) = Farc::parse(&input).unwrap();
+        assert_eq!(&farc.get(NAME).unwrap().data().unwrap()[..], INPUT);
+    }
+
+    #[test]
+    fn read_encrypted() {
+        use aes::cipher::BlockEncrypt;
+        let mut enc = GzEncoder::new(vec![], flate2::Compression::fast());
+        enc.write_all(INPUT).unwrap();
+        let mut data = enc.finish().unwrap();
+        let stored_size = data.len() as u32;
+        data.resize((data.len() + 15) & !15, 0);
+        let cipher = Aes128::new(GenericArray::from_slice(KEY));
+        for block in data.chunks_exact_mut(16) {
+            cipher.encrypt_block(GenericArray::from_mut_slice(block));
+        }
+        let flags = FLAG_COMPRESSED | FLAG_ENCRYPTED;
+        let mut input = header(
+            b"FARC",
+            &[flags, 0, 0x40, 0, 1],
+            &[stored_size, INPUT.len() as u32],
+        );
+        input.extend(&data);
+        let (_, farc) = Farc::parse(&input).unwrap();
+        assert_eq!(farc.signature, Signature::Extended);
+        assert_eq!(&farc.get(NAME).unwrap().data().unwrap()[..], INPUT);
+    }
+
+    #[test]
+    fn read_path() {
+        let mut input = header(b"FArc", &[0x20], &[INPUT.len() as u32]);
+        input.extend(INPUT);
+        let path = std::env::temp_dir().join("txp_read_path.farc");
+        std::fs::write(&path, input).unwrap();
+        let data = read(format!("{}!/{}", path.display(), NAME)).unwrap();
+        assert_eq!(data, INPUT);
+        let missing = read(format!("{}!/missing.bin", path.display())).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn write_roundtrip() {
+        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        for signature in [Signature::Stored, Signature::Compressed] {
+            let mut farc = Farc::new(signature);
+            farc.push(NAME, atlas.try_to_bytes().unwrap()).unwrap();
+            farc.push("empty.bin", vec![]).unwrap();
+            let mut out = vec![];
+            farc.write(&mut out).unwrap();
+            let (_, read) = Farc::parse(&out).unwrap();
+            assert_eq!(read.signature, signature);
+            assert_eq!(read.entries.len(), 2);
+            let data = read.get(NAME).unwrap().data().unwrap();
+            let (_, reparsed) = TextureAtlas::parse(&data).unwrap();
+            assert_eq!(atlas, reparsed);
+            assert!(read.get("empty.bin").unwrap().data().unwrap().is_empty());
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use ::image::dxt::{DXTVariant, DxtDecoder};
+use ::image::dxt::{DXTVariant, DxtDecoder, DxtEncoder};
 use ::image::*;
 
 use super::*;
@@ -20,7 +20,7 @@ impl<'a> Mipmap<'a> {
     pub fn to_rgb(&self) -> Option<ImageBuffer<Rgb<u8>, &[u8]>> {
         use TextureFormat::*;
         match self.format {
-            RGB => ImageBuffer::from_raw(self.width, self.height, &self.data),
+            RGB8 => ImageBuffer::from_raw(self.width, self.height, &self.data),
             _ => None,
         }
     }
@@ -28,7 +28,7 @@ impl<'a> Mipmap<'a> {
     pub fn to_rgba(&self) -> Option<ImageBuffer<Rgba<u8>, &[u8]>> {
         use TextureFormat::*;
         match self.format {
-            RGBA => ImageBuffer::from_raw(self.width, self.height, &self.data),
+            RGBA8 => ImageBuffer::from_raw(self.width, self.height, &self.data),
             _ => None,
         }
     }
@@ -42,28 +42,42 @@ impl<'a> Mipmap<'a> {
     }
 
     pub fn to_luma_alpha(&self) -> Option<ImageBuffer<LumaA<u8>, &[u8]>> {
+        use TextureFormat::*;
         match self.format {
             L8A8 => ImageBuffer::from_raw(self.width, self.height, &self.data),
             _ => None,
         }
     }
 
+    /// Decodes the mip into the closest [`DynamicImage`], `None` if its data is too short.
+    ///
+    /// Uncompressed formats keep their channels, DXT1 and ATI2 become RGB and ATI1 luma,
+    /// everything else is RGBA from [`Mipmap::decode_rgba8`]
     pub fn to_dynamic_image(self) -> Option<DynamicImage> {
         use TextureFormat::*;
         match self.format {
-            RGB => ImageBuffer::from_raw(self.width, self.height, self.data.into_owned())
+            RGB8 => ImageBuffer::from_raw(self.width, self.height, self.data.into_owned())
                 .map(DynamicImage::ImageRgb8),
-            RGBA => ImageBuffer::from_raw(self.width, self.height, self.data.into_owned())
+            RGBA8 => ImageBuffer::from_raw(self.width, self.height, self.data.into_owned())
                 .map(DynamicImage::ImageRgba8),
             L8 => ImageBuffer::from_raw(self.width, self.height, self.data.into_owned())
                 .map(DynamicImage::ImageLuma8),
             L8A8 => ImageBuffer::from_raw(self.width, self.height, self.data.into_owned())
                 .map(DynamicImage::ImageLumaA8),
-            DXT1 | DXT1a | DXT3 | DXT5 => {
-                let dec = self.to_dxt_decoder()?.ok()?;
-                DynamicImage::from_decoder(dec).ok()
+            ATI1 => {
+                let rgba = self.decode_rgba8().ok()?;
+                let luma = rgba.chunks_exact(4).map(|x| x[0]).collect();
+                ImageBuffer::from_raw(self.width, self.height, luma).map(DynamicImage::ImageLuma8)
+            }
+            format => {
+                let rgba =
+                    ImageBuffer::from_raw(self.width, self.height, self.decode_rgba8().ok()?)
+                        .map(DynamicImage::ImageRgba8)?;
+                match format {
+                    DXT1 | ATI2 => Some(DynamicImage::ImageRgb8(rgba.to_rgb8())),
+                    _ => Some(rgba),
+                }
             }
-            _ => return None,
         }
     }
 
@@ -73,16 +87,274 @@ impl<'a> Mipmap<'a> {
     {
         use TextureFormat::*;
         Some(match self.format {
-            RGB => self.to_rgb()?.save(path),
-            RGBA => self.to_rgba()?.save(path),
+            RGB8 => self.to_rgb()?.save(path),
+            RGBA8 => self.to_rgba()?.save(path),
             L8 => self.to_luma()?.save(path),
             L8A8 => self.to_luma_alpha()?.save(path),
+            _ => self.clone().to_dynamic_image()?.save(path),
+        })
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn map<T: Sync, U: Send>(items: &[T], f: impl Fn(&T) -> U + Sync + Send) -> Vec<U> {
+    use rayon::prelude::*;
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+fn map<T, U>(items: &[T], f: impl Fn(&T) -> U) -> Vec<U> {
+    items.iter().map(f).collect()
+}
+
+impl TextureAtlas<'_> {
+    /// Decodes every mip, nested like the atlas, `None` for mips without a decoder.
+    ///
+    /// With the `rayon` feature textures, subtextures and mips are decoded in parallel.
+    pub fn to_dynamic_images(&self) -> Vec<Vec<Vec<Option<DynamicImage>>>> {
+        map(&self.0, Texture::to_dynamic_images)
+    }
+}
+
+impl Texture<'_> {
+    /// Decodes the image of every subtexture, as meant to be displayed according to [`Texture::kind`].
+    ///
+    /// That's the largest mip of each subtexture, except for [`TextureKind::Yuv`]
+    /// where both mips are combined into a single image.
+    pub fn to_images(&self) -> Vec<Option<DynamicImage>> {
+        match self.kind() {
+            TextureKind::Yuv => vec![self.subtextures[0].to_image()],
+            _ => map(&self.subtextures, |sub| {
+                sub.mipmaps.first()?.clone().to_dynamic_image()
+            }),
+        }
+    }
+
+    /// Decodes every mip of every subtexture, see [`TextureAtlas::to_dynamic_images`]
+    pub fn to_dynamic_images(&self) -> Vec<Vec<Option<DynamicImage>>> {
+        map(&self.subtextures, |sub| {
+            map(&sub.mipmaps, |mip| mip.clone().to_dynamic_image())
+        })
+    }
+}
+
+impl Subtexture<'_> {
+    /// Decodes the image this subtexture displays on its own.
+    ///
+    /// A pair of ATI2 mips is taken as YUV, like in [`Texture::to_images`]
+    pub fn to_image(&self) -> Option<DynamicImage> {
+        let formats: Vec<_> = self.mipmaps.iter().map(|x| x.format).collect();
+        match TextureKind::classify(1, &formats) {
+            TextureKind::Yuv => {
+                let (w, h) = (self.mipmaps[0].width, self.mipmaps[0].height);
+                let rgba = self.decode_rgba8().ok()?;
+                ImageBuffer::from_raw(w, h, rgba).map(DynamicImage::ImageRgba8)
+            }
+            _ => self.mipmaps.first()?.clone().to_dynamic_image(),
+        }
+    }
+}
+
+impl TextureFormat {
+    /// Whether [`Mipmap::from_image`] has an encoder for this format
+    pub fn can_encode(&self) -> bool {
+        !matches!(self, TextureFormat::BC7 | TextureFormat::BC6H)
+    }
+}
+
+impl Mipmap<'static> {
+    /// Encodes `image` into `format`, `None` if there is no encoder for the format.
+    ///
+    /// Block compressed formats are padded to a multiple of 4 by repeating the edges.
+    pub fn from_image(image: &DynamicImage, format: TextureFormat) -> Option<Self> {
+        use TextureFormat::*;
+        let (width, height) = image.dimensions();
+        let data = match format {
+            A8 => image.to_rgba8().pixels().map(|x| x.0[3]).collect(),
+            RGB8 => image.to_rgb8().into_raw(),
+            RGBA8 => image.to_rgba8().into_raw(),
+            L8 => image.to_luma8().into_raw(),
+            L8A8 => image.to_luma_alpha8().into_raw(),
+            RGB5 => pack_u16(image, |[r, g, b, _]| {
+                (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3
+            }),
+            RGB5A1 => pack_u16(image, |[r, g, b, a]| {
+                ((a >= 128) as u16) << 15
+                    | (r as u16 >> 3) << 10
+                    | (g as u16 >> 3) << 5
+                    | b as u16 >> 3
+            }),
+            RGBA4 => pack_u16(image, |[r, g, b, a]| {
+                (a as u16 >> 4) << 12 | (r as u16 >> 4) << 8 | (g as u16 >> 4) << 4 | b as u16 >> 4
+            }),
             DXT1 | DXT1a | DXT3 | DXT5 => {
-                let dec = self.to_dxt_decoder()?.ok()?;
-                let image = DynamicImage::from_decoder(dec).ok()?;
-                image.save(path)
+                let padded = pad_to_blocks(image);
+                let (w, h) = padded.dimensions();
+                let (variant, raw) = match format {
+                    DXT3 => (DXTVariant::DXT3, padded.into_raw()),
+                    DXT5 => (DXTVariant::DXT5, padded.into_raw()),
+                    _ => (
+                        DXTVariant::DXT1,
+                        DynamicImage::ImageRgba8(padded).to_rgb8().into_raw(),
+                    ),
+                };
+                let mut out = vec![];
+                DxtEncoder::new(&mut out).encode(&raw, w, h, variant).ok()?;
+                out
             }
-            _ => return None,
+            ATI1 => encode_bc4(&pad_to_blocks(image), 1),
+            ATI2 => encode_bc4(&pad_to_blocks(image), 2),
+            BC7 | BC6H => return None,
+        };
+        Some(Self {
+            id: 0,
+            width,
+            height,
+            format,
+            data: data.into(),
+        })
+    }
+}
+
+impl Texture<'static> {
+    /// Encodes `image` into `format` along with `mip_count - 1` smaller mips, each halving the last.
+    ///
+    /// Like [`Mipmap::from_image`], `image` is stored as is, without flipping it.
+    pub fn from_image(image: &DynamicImage, format: TextureFormat, mip_count: u32) -> Option<Self> {
+        let (width, height) = image.dimensions();
+        let mipmaps = (0..mip_count.max(1))
+            .map(|level| {
+                let (w, h) = ((width >> level).max(1), (height >> level).max(1));
+                let mip = match level {
+                    0 => Mipmap::from_image(image, format),
+                    _ => {
+                        let resized = image.resize_exact(w, h, imageops::FilterType::Triangle);
+                        Mipmap::from_image(&resized, format)
+                    }
+                };
+                mip.map(|mip| Mipmap { id: level, ..mip })
+            })
+            .collect::<Option<_>>()?;
+        Some(Texture {
+            subtextures: vec![Subtexture { mipmaps }],
+        })
+    }
+}
+
+fn pack_u16(image: &DynamicImage, f: impl Fn([u8; 4]) -> u16) -> Vec<u8> {
+    image
+        .to_rgba8()
+        .pixels()
+        .flat_map(|x| f(x.0).to_le_bytes())
+        .collect()
+}
+
+fn pad_to_blocks(image: &DynamicImage) -> RgbaImage {
+    let rgba = image.to_rgba8();
+    let (w, h) = rgba.dimensions();
+    let (pw, ph) = (w.div_ceil(4) * 4, h.div_ceil(4) * 4);
+    ImageBuffer::from_fn(pw, ph, |x, y| *rgba.get_pixel(x.min(w - 1), y.min(h - 1)))
+}
+
+/// Encodes the first `channels` channels as consecutive BC4 blocks, which is ATI1/ATI2
+fn encode_bc4(image: &RgbaImage, channels: usize) -> Vec<u8> {
+    let (w, h) = image.dimensions();
+    let mut out = vec![];
+    for by in (0..h).step_by(4) {
+        for bx in (0..w).step_by(4) {
+            for c in 0..channels {
+                let mut values = [0u8; 16];
+                for (i, v) in values.iter_mut().enumerate() {
+                    *v = image.get_pixel(bx + i as u32 % 4, by + i as u32 / 4).0[c];
+                }
+                out.extend(encode_bc4_block(&values));
+            }
+        }
+    }
+    out
+}
+
+fn encode_bc4_block(values: &[u8; 16]) -> [u8; 8] {
+    let max = *values.iter().max().unwrap();
+    let min = *values.iter().min().unwrap();
+    let mut block = [max, min, 0, 0, 0, 0, 0, 0];
+    if max == min {
+        return block;
+    }
+    // with a0 > a1, index 0 and 1 are the endpoints and 2..=7 interpolate between them
+    let palette: Vec<u32> = (0..8u32)
+        .map(|i| match i {
+            0 => max as u32,
+            1 => min as u32,
+            _ => ((8 - i) * max as u32 + (i - 1) * min as u32) / 7,
         })
+        .collect();
+    let mut bits = 0u64;
+    for (i, &v) in values.iter().enumerate() {
+        let index = (0..8)
+            .min_by_key(|&j| (palette[j] as i32 - v as i32).abs())
+            .unwrap() as u64;
+        bits |= index << (3 * i);
+    }
+    block[2..].copy_from_slice(&bits.to_le_bytes()[..6]);
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_atlas() {
+        let input = include_bytes!("../assets/spr_sel_pv616.bin");
+        let (_, atlas) = TextureAtlas::parse(input).unwrap();
+        let images = atlas.to_dynamic_images();
+        assert_eq!(images.len(), 2);
+        let mip = &atlas.0[1].subtextures[0].mipmaps[0];
+        let dec = DxtDecoder::new(&mip.data[..], mip.width, mip.height, DXTVariant::DXT5).unwrap();
+        let expected = DynamicImage::from_decoder(dec).unwrap();
+        assert_eq!(
+            images[1][0][0].as_ref().unwrap().as_bytes(),
+            expected.as_bytes()
+        );
+        assert_eq!(images[1][0].len(), atlas.0[1].subtextures[0].mipmaps.len());
+    }
+
+    #[test]
+    fn decode_yuv() {
+        let input = include_bytes!("../assets/spr_sel_pv616.bin");
+        let (_, atlas) = TextureAtlas::parse(input).unwrap();
+        let images = atlas.0[0].to_images();
+        assert_eq!(images.len(), 1);
+        let image = images[0].as_ref().unwrap();
+        assert_eq!(image.dimensions(), (1024, 512));
+        assert_eq!(atlas.0[1].to_images().len(), 1);
+    }
+
+    #[test]
+    fn bc4_roundtrip() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(8, 6, |x, y| {
+            Rgba([(x * 30) as u8, (y * 40) as u8, 0, 255])
+        }));
+        let mip = Mipmap::from_image(&image, TextureFormat::ATI2).unwrap();
+        let decoded = mip.to_dynamic_image().unwrap().to_rgb8();
+        for (a, b) in image.to_rgb8().pixels().zip(decoded.pixels()) {
+            assert!((a.0[0] as i32 - b.0[0] as i32).abs() <= 8);
+            assert!((a.0[1] as i32 - b.0[1] as i32).abs() <= 8);
+        }
+    }
+
+    #[test]
+    fn texture_from_image() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(16, 8));
+        let tex = Texture::from_image(&image, TextureFormat::DXT5, 5).unwrap();
+        let mips = &tex.subtextures[0].mipmaps;
+        let dims: Vec<_> = mips.iter().map(|x| (x.id, x.width, x.height)).collect();
+        assert_eq!(
+            dims,
+            [(0, 16, 8), (1, 8, 4), (2, 4, 2), (3, 2, 1), (4, 1, 1)]
+        );
+        assert_eq!(tex.kind(), TextureKind::Mipmapped);
+        assert!(TextureAtlas(vec![tex]).validate().is_ok());
     }
 }
@@ -3,6 +3,165 @@ use std::fmt::Display;
 
 impl Display for Mipmap<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "SubTex {}x{} {:?}", self.width, self.height, self.format)
+        write!(
+            f,
+            "SubTex #{} {}x{} {:?}",
+            self.id, self.width, self.height, self.format
+        )
+    }
+}
+
+impl TextureAtlas<'_> {
+    /// Copies any borrowed data, detaching the atlas from its input
+    pub fn into_owned(self) -> TextureAtlas<'static> {
+        TextureAtlas(self.0.into_iter().map(Texture::into_owned).collect())
+    }
+}
+
+impl Texture<'_> {
+    pub fn into_owned(self) -> Texture<'static> {
+        let subtextures = self
+            .subtextures
+            .into_iter()
+            .map(Subtexture::into_owned)
+            .collect();
+        Texture { subtextures }
+    }
+}
+
+impl TextureKind {
+    /// Classifies a texture by its amount of subtextures and the mip formats of the first one
+    pub(crate) fn classify(subtextures: usize, formats: &[TextureFormat]) -> Self {
+        use TextureFormat::ATI2;
+        match (subtextures, formats) {
+            (1, [ATI2, ATI2]) => Self::Yuv,
+            (1, [_]) | (1, []) => Self::Plain,
+            (1, _) => Self::Mipmapped,
+            (6, _) => Self::Cubemap,
+            _ => Self::Array,
+        }
+    }
+}
+
+impl Texture<'_> {
+    pub fn kind(&self) -> TextureKind {
+        let formats: Vec<_> = self
+            .subtextures
+            .first()
+            .map(|x| x.mipmaps.iter().map(|x| x.format).collect())
+            .unwrap_or_default();
+        TextureKind::classify(self.subtextures.len(), &formats)
+    }
+}
+
+impl Subtexture<'_> {
+    pub fn into_owned(self) -> Subtexture<'static> {
+        let mipmaps = self.mipmaps.into_iter().map(Mipmap::into_owned).collect();
+        Subtexture { mipmaps }
+    }
+}
+
+impl Mipmap<'_> {
+    pub fn into_owned(self) -> Mipmap<'static> {
+        Mipmap {
+            data: Cow::Owned(self.data.into_owned()),
+            ..self
+        }
+    }
+}
+
+impl TextureFormat {
+    /// Whether the format is stored in 4x4 blocks
+    pub fn is_compressed(&self) -> bool {
+        self.block_size().is_some()
+    }
+
+    /// Bytes per 4x4 block, `None` for uncompressed formats
+    pub fn block_size(&self) -> Option<usize> {
+        use TextureFormat::*;
+        match self {
+            DXT1 | DXT1a | ATI1 => Some(8),
+            DXT3 | DXT5 | ATI2 | BC7 | BC6H => Some(16),
+            _ => None,
+        }
+    }
+
+    /// Bytes per pixel, `None` for block compressed formats
+    pub fn bytes_per_pixel(&self) -> Option<usize> {
+        use TextureFormat::*;
+        match self {
+            A8 | L8 => Some(1),
+            RGB5 | RGB5A1 | RGBA4 | L8A8 => Some(2),
+            RGB8 => Some(3),
+            RGBA8 => Some(4),
+            _ => None,
+        }
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        use TextureFormat::*;
+        matches!(
+            self,
+            A8 | RGBA8 | RGB5A1 | RGBA4 | DXT1a | DXT3 | DXT5 | L8A8 | BC7
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn into_owned() {
+        let input = std::fs::read("assets/mikitm001_tex.txp").unwrap();
+        let (_, atlas) = TextureAtlas::parse(&input).unwrap();
+        let owned = atlas.clone().into_owned();
+        assert_eq!(atlas, owned);
+        drop(atlas);
+        drop(input);
+        let handle = std::thread::spawn(move || owned.0.len());
+        assert_eq!(handle.join().unwrap(), 18);
+    }
+
+    #[test]
+    fn texture_kind() {
+        let input = std::fs::read("assets/spr_sel_pv616.bin").unwrap();
+        let (_, atlas) = TextureAtlas::parse(&input).unwrap();
+        assert_eq!(atlas.0[0].kind(), TextureKind::Yuv);
+        let mip = Mipmap::default();
+        let sub = |n| Subtexture {
+            mipmaps: vec![mip.clone(); n],
+        };
+        let tex = |subs, mips| Texture {
+            subtextures: vec![sub(mips); subs],
+        };
+        assert_eq!(tex(1, 1).kind(), TextureKind::Plain);
+        assert_eq!(tex(1, 3).kind(), TextureKind::Mipmapped);
+        assert_eq!(tex(6, 3).kind(), TextureKind::Cubemap);
+        assert_eq!(tex(2, 1).kind(), TextureKind::Array);
+    }
+
+    #[test]
+    fn format_sizes() {
+        let input = std::fs::read("assets/mikitm001_tex.txp").unwrap();
+        let (_, atlas) = TextureAtlas::parse(&input).unwrap();
+        let mips = atlas
+            .0
+            .iter()
+            .flat_map(|x| &x.subtextures)
+            .flat_map(|x| &x.mipmaps);
+        // The smallest mips of compressed textures are truncated
+        for mip in mips.filter(|x| x.width >= 4 && x.height >= 4) {
+            let (w, h) = (mip.width as usize, mip.height as usize);
+            let size = match mip.format.block_size() {
+                Some(block) => (w / 4) * (h / 4) * block,
+                None => w * h * mip.format.bytes_per_pixel().unwrap(),
+            };
+            assert_eq!(size, mip.data.len(), "{}", mip);
+        }
+        assert!(TextureFormat::DXT5.is_compressed());
+        assert!(!TextureFormat::L8A8.is_compressed());
+        assert!(TextureFormat::L8A8.has_alpha());
+        assert!(!TextureFormat::ATI2.has_alpha());
     }
 }
@@ -0,0 +1,198 @@
+//! Atlas metadata without the pixel data, for listing huge atlases quickly.
+use std::convert::TryInto;
+
+use nom::branch::alt;
+use nom::bytes::complete::take;
+use nom::combinator::map_opt;
+use nom::number::complete::u32;
+use nom::IResult;
+use nom::Parser;
+use tracing::{debug, trace};
+
+use super::read::{offset_table, parse_magic, parse_texture};
+use super::*;
+
+/// Everything about a mip except its data
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct MipmapHeader {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    /// Offset of the data from the start of the atlas
+    pub offset: usize,
+    /// Length of the data in bytes
+    pub len: usize,
+}
+
+/// The layout of a [`TextureAtlas`], mip data is only read by the `load` methods
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct TextureAtlasIndex(pub Vec<TextureIndex>);
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct TextureIndex {
+    pub subtextures: Vec<SubtextureIndex>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SubtextureIndex {
+    pub mipmaps: Vec<MipmapHeader>,
+}
+
+impl TextureAtlasIndex {
+    /// Walks the same structure as [`TextureAtlas::parse`] without borrowing any data
+    #[tracing::instrument(name = "atlas_index", skip(i0))]
+    pub fn parse(i0: &[u8]) -> IResult<&[u8], TextureAtlasIndex> {
+        let (i, endian) = parse_magic(3)(i0)?;
+        let (i, map_count) = u32(endian)(i)?;
+        let (i, _unk) = u32(endian)(i)?;
+        debug!(?endian, map_count);
+        let parse = alt((
+            |i| TextureIndex::parse(i0, 4, i),
+            |i| TextureIndex::parse(i0, 5, i),
+        ));
+        let (_, maps) = offset_table(i0, parse, map_count.try_into().unwrap(), endian).parse(i)?;
+        Ok((i, Self(maps)))
+    }
+
+    pub fn mipmap(&self, texture: usize, subtexture: usize, mip: usize) -> Option<&MipmapHeader> {
+        self.0
+            .get(texture)?
+            .subtextures
+            .get(subtexture)?
+            .mipmaps
+            .get(mip)
+    }
+
+    /// Materializes every texture from the `atlas` this index was parsed from
+    pub fn load<'a>(&self, atlas: &'a [u8]) -> Option<TextureAtlas<'a>> {
+        self.0
+            .iter()
+            .map(|x| x.load(atlas))
+            .collect::<Option<_>>()
+            .map(TextureAtlas)
+    }
+}
+
+impl TextureIndex {
+    fn parse<'a>(a0: &'a [u8], id: u8, i0: &'a [u8]) -> IResult<&'a [u8], TextureIndex> {
+        let (i, subtextures) = parse_texture(id, i0, |i| MipmapHeader::parse(a0, i))?;
+        let subtextures = subtextures
+            .into_iter()
+            .map(|mipmaps| SubtextureIndex { mipmaps })
+            .collect();
+        Ok((i, Self { subtextures }))
+    }
+
+    /// Same as [`Texture::kind`], from the headers alone
+    pub fn kind(&self) -> TextureKind {
+        let formats: Vec<_> = self
+            .subtextures
+            .first()
+            .map(|x| x.mipmaps.iter().map(|x| x.format).collect())
+            .unwrap_or_default();
+        TextureKind::classify(self.subtextures.len(), &formats)
+    }
+
+    pub fn load<'a>(&self, atlas: &'a [u8]) -> Option<Texture<'a>> {
+        let subtextures = self
+            .subtextures
+            .iter()
+            .map(|x| x.load(atlas))
+            .collect::<Option<_>>()?;
+        Some(Texture { subtextures })
+    }
+}
+
+impl SubtextureIndex {
+    pub fn load<'a>(&self, atlas: &'a [u8]) -> Option<Subtexture<'a>> {
+        let mipmaps = self
+            .mipmaps
+            .iter()
+            .map(|x| x.load(atlas))
+            .collect::<Option<_>>()?;
+        Some(Subtexture { mipmaps })
+    }
+}
+
+impl MipmapHeader {
+    fn parse<'a>(a0: &'a [u8], i: &'a [u8]) -> IResult<&'a [u8], MipmapHeader> {
+        let (i, endian) = parse_magic(2)(i)?;
+        let (i, width) = u32(endian)(i)?;
+        let (i, height) = u32(endian)(i)?;
+        let (i, format) = map_opt(u32(endian), TextureFormat::from_id)(i)?;
+        let (i, id) = u32(endian)(i)?;
+        let (i, len) = u32(endian)(i)?;
+        // Every input is a suffix of the atlas
+        let offset = a0.len() - i.len();
+        let (i, _) = take(len)(i)?;
+        trace!(width, height, ?format, id, offset, len);
+        Ok((
+            i,
+            Self {
+                id,
+                width,
+                height,
+                format,
+                offset,
+                len: len as usize,
+            },
+        ))
+    }
+
+    /// Borrows the data of this mip from the `atlas` it was read from
+    pub fn load<'a>(&self, atlas: &'a [u8]) -> Option<Mipmap<'a>> {
+        let data = atlas.get(self.offset..self.offset + self.len)?;
+        Some(Mipmap {
+            id: self.id,
+            width: self.width,
+            height: self.height,
+            format: self.format,
+            data: data.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &[u8] = include_bytes!("../assets/mikitm001_tex.txp");
+    const YUV: &[u8] = include_bytes!("../assets/spr_sel_pv616.bin");
+
+    #[test]
+    fn index_atlas() {
+        let (_, index) = TextureAtlasIndex::parse(INPUT).unwrap();
+        assert_eq!(index.0.len(), 18);
+        let mip = index.mipmap(1, 0, 0).unwrap();
+        assert_eq!(
+            (mip.width, mip.height, mip.format),
+            (256, 512, TextureFormat::DXT1)
+        );
+        assert_eq!(mip.len, 65536);
+    }
+
+    #[test]
+    fn load_on_demand() {
+        for input in [INPUT, YUV] {
+            let (_, atlas) = TextureAtlas::parse(input).unwrap();
+            let (_, index) = TextureAtlasIndex::parse(input).unwrap();
+            let mip = index.mipmap(0, 0, 1).or(index.mipmap(0, 0, 0)).unwrap();
+            assert_eq!(mip.load(input).unwrap().id, mip.id);
+            assert_eq!(index.0[0].load(input).unwrap(), atlas.0[0]);
+            assert_eq!(index.load(input).unwrap(), atlas);
+            assert_eq!(index.load(&input[..100]), None);
+        }
+    }
+
+    #[test]
+    fn index_empty_array() {
+        #[rustfmt::skip]
+        let input = [
+            0x54, 0x58, 0x50, 3, 1, 0, 0, 0, 0, 0, 0, 0, 16, 0, 0, 0,
+            0x54, 0x58, 0x50, 5, 1, 0, 0, 0, 0, 0, 0, 0, 12, 0, 0, 0,
+        ];
+        assert!(TextureAtlasIndex::parse(&input).is_err());
+        assert!(TextureAtlas::parse(&input).is_err());
+    }
+}
@@ -0,0 +1,303 @@
+//! Writing textures as KTX2 containers, without any supercompression.
+use std::io::{self, Write};
+
+use tracing::debug;
+
+use super::*;
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+/// Identifier, header and index
+const HEADER_SIZE: usize = 12 + 9 * 4 + 4 * 4 + 2 * 8;
+
+// Khronos data format color models
+const MODEL_RGBSDA: u8 = 1;
+const MODEL_BC1A: u8 = 128;
+const MODEL_BC2: u8 = 129;
+const MODEL_BC3: u8 = 130;
+const MODEL_BC4: u8 = 131;
+const MODEL_BC5: u8 = 132;
+const MODEL_BC6H: u8 = 133;
+const MODEL_BC7: u8 = 134;
+
+const RED: u8 = 0;
+const GREEN: u8 = 1;
+const BLUE: u8 = 2;
+const ALPHA: u8 = 15;
+const FLOAT: u8 = 0x80;
+
+/// A sample of the data format descriptor, `(channel, bit offset, bit length, upper)`
+type Sample = (u8, u16, u8, u32);
+
+/// How a format is laid out, `(vkFormat, color model, block size, block bytes, samples, swizzle)`
+type Layout = (u32, u8, u8, u8, &'static [Sample], Option<&'static str>);
+
+impl TextureFormat {
+    /// The Vulkan format and data format descriptor of this format, `None` for the packed 16 bit ones
+    fn ktx2_layout(&self) -> Option<Layout> {
+        use TextureFormat::*;
+        const R: &[Sample] = &[(RED, 0, 8, 255)];
+        const RG: &[Sample] = &[(RED, 0, 8, 255), (GREEN, 8, 8, 255)];
+        const RGB: &[Sample] = &[(RED, 0, 8, 255), (GREEN, 8, 8, 255), (BLUE, 16, 8, 255)];
+        const RGBA: &[Sample] = &[
+            (RED, 0, 8, 255),
+            (GREEN, 8, 8, 255),
+            (BLUE, 16, 8, 255),
+            (ALPHA, 24, 8, 255),
+        ];
+        const BLOCK: &[Sample] = &[(0, 0, 64, u32::MAX)];
+        const ALPHA_BLOCK: &[Sample] = &[(1, 0, 64, u32::MAX)];
+        const BLOCK128: &[Sample] = &[(0, 0, 128, u32::MAX)];
+        const TWO_BLOCKS: &[Sample] = &[(ALPHA, 0, 64, u32::MAX), (0, 64, 64, u32::MAX)];
+        const RG_BLOCKS: &[Sample] = &[(RED, 0, 64, u32::MAX), (GREEN, 64, 64, u32::MAX)];
+        const FLOAT_BLOCK: &[Sample] = &[(FLOAT, 0, 128, 0x3F80_0000)];
+        Some(match self {
+            A8 => (9, MODEL_RGBSDA, 1, 1, R, Some("000r")),
+            L8 => (9, MODEL_RGBSDA, 1, 1, R, Some("rrr1")),
+            L8A8 => (16, MODEL_RGBSDA, 1, 2, RG, Some("rrrg")),
+            RGB8 => (23, MODEL_RGBSDA, 1, 3, RGB, None),
+            RGBA8 => (37, MODEL_RGBSDA, 1, 4, RGBA, None),
+            DXT1 => (131, MODEL_BC1A, 4, 8, BLOCK, None),
+            DXT1a => (133, MODEL_BC1A, 4, 8, ALPHA_BLOCK, None),
+            DXT3 => (135, MODEL_BC2, 4, 16, TWO_BLOCKS, None),
+            DXT5 => (137, MODEL_BC3, 4, 16, TWO_BLOCKS, None),
+            ATI1 => (139, MODEL_BC4, 4, 8, BLOCK, None),
+            ATI2 => (141, MODEL_BC5, 4, 16, RG_BLOCKS, None),
+            BC6H => (143, MODEL_BC6H, 4, 16, FLOAT_BLOCK, None),
+            BC7 => (145, MODEL_BC7, 4, 16, BLOCK128, None),
+            RGB5 | RGB5A1 | RGBA4 => return None,
+        })
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Basic data format descriptor, prefixed by its total size
+fn dfd(model: u8, block: u8, bytes: u8, samples: &[Sample]) -> Vec<u8> {
+    let block_size = 24 + 16 * samples.len() as u32;
+    let words = [
+        0,
+        2 | block_size << 16,
+        // BT.709 primaries, linear transfer
+        model as u32 | 1 << 8 | 1 << 16,
+        (block as u32 - 1) | (block as u32 - 1) << 8,
+        bytes as u32,
+        0,
+    ];
+    let mut out: Vec<u8> = (block_size + 4).to_le_bytes().to_vec();
+    out.extend(words.iter().flat_map(|x| x.to_le_bytes()));
+    for &(channel, offset, length, upper) in samples {
+        let sample = [
+            offset as u32 | (length as u32 - 1) << 16 | (channel as u32) << 24,
+            0,
+            0,
+            upper,
+        ];
+        out.extend(sample.iter().flat_map(|x| x.to_le_bytes()));
+    }
+    out
+}
+
+/// A single key/value entry, padded to 4 bytes
+fn key_value(key: &str, value: &str) -> Vec<u8> {
+    let len = key.len() + value.len() + 2;
+    let mut out = (len as u32).to_le_bytes().to_vec();
+    out.extend(key.bytes().chain([0]).chain(value.bytes()).chain([0]));
+    out.resize(4 + len.div_ceil(4) * 4, 0);
+    out
+}
+
+fn align(offset: usize, to: usize) -> usize {
+    offset.div_ceil(to) * to
+}
+
+fn lcm4(x: usize) -> usize {
+    match x % 4 {
+        0 => x,
+        2 => x * 2,
+        _ => x * 4,
+    }
+}
+
+/// Bytes of `level` in a ktx2, whole blocks of a level at least 1 pixel wide and high
+fn level_len(width: u32, height: u32, level: usize, block: u8, bytes: u8) -> usize {
+    let size = |x: u32| ((x >> level).max(1) as usize).div_ceil(block as usize);
+    size(width) * size(height) * bytes as usize
+}
+
+impl Texture<'_> {
+    /// Serializes the texture as a `.ktx2`, cubemaps and arrays included.
+    ///
+    /// Mips are padded or cut to the whole blocks a ktx2 expects, as games store the smallest
+    /// ones truncated. The packed 16 bit formats have no equivalent and fail with
+    /// [`io::ErrorKind::Unsupported`]
+    #[tracing::instrument(skip(self, w))]
+    pub fn write_ktx2<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let first = self
+            .subtextures
+            .first()
+            .and_then(|x| x.mipmaps.first())
+            .ok_or_else(|| invalid("texture has no mipmaps".into()))?;
+        let levels = self.subtextures[0].mipmaps.len();
+        for mip in self.subtextures.iter().flat_map(|x| &x.mipmaps) {
+            if mip.format != first.format {
+                return Err(invalid(format!(
+                    "mixed formats {:?} and {:?}",
+                    first.format, mip.format
+                )));
+            }
+        }
+        if self.subtextures.iter().any(|x| x.mipmaps.len() != levels) {
+            return Err(invalid(
+                "all subtextures must have the same amount of mipmaps".into(),
+            ));
+        }
+        let (vk_format, model, block, bytes, samples, swizzle) =
+            first.format.ktx2_layout().ok_or_else(|| {
+                let msg = format!("{:?} cannot be stored in a ktx2", first.format);
+                io::Error::new(io::ErrorKind::Unsupported, msg)
+            })?;
+        let (width, height) = (first.width.max(1), first.height.max(1));
+        let (layers, faces) = match self.kind() {
+            TextureKind::Cubemap => (0, 6),
+            TextureKind::Array => (self.subtextures.len() as u32, 1),
+            _ => (0, 1),
+        };
+        debug!(vk_format, layers, faces, levels);
+
+        let dfd = dfd(model, block, bytes, samples);
+        let kvd = swizzle
+            .map(|x| key_value("KTXswizzle", x))
+            .unwrap_or_default();
+        let dfd_offset = HEADER_SIZE + levels * 24;
+        let kvd_offset = dfd_offset + dfd.len();
+        // Levels are stored smallest first, each aligned to its texel block
+        let level_align = lcm4(bytes as usize);
+        let mut offset = kvd_offset + kvd.len();
+        let mut index = vec![(0, 0); levels];
+        for level in (0..levels).rev() {
+            offset = align(offset, level_align);
+            let len = level_len(width, height, level, block, bytes);
+            index[level] = (offset, len * self.subtextures.len());
+            offset += len;
+        }
+
+        w.write_all(&IDENTIFIER)?;
+        let header = [
+            vk_format,
+            1,
+            width,
+            height,
+            0,
+            layers,
+            faces,
+            levels as u32,
+            0,
+            dfd_offset as u32,
+            dfd.len() as u32,
+            if kvd.is_empty() { 0 } else { kvd_offset as u32 },
+            kvd.len() as u32,
+        ];
+        for x in header {
+            w.write_all(&x.to_le_bytes())?;
+        }
+        // No supercompression global data
+        w.write_all(&[0; 16])?;
+        for &(offset, len) in &index {
+            for x in [offset, len, len] {
+                w.write_all(&(x as u64).to_le_bytes())?;
+            }
+        }
+        w.write_all(&dfd)?;
+        w.write_all(&kvd)?;
+        let mut written = kvd_offset + kvd.len();
+        for level in (0..levels).rev() {
+            let (offset, len) = index[level];
+            w.write_all(&vec![0; offset - written])?;
+            let mip_len = level_len(width, height, level, block, bytes);
+            for sub in &self.subtextures {
+                let data = &sub.mipmaps[level].data;
+                let data = &data[..data.len().min(mip_len)];
+                w.write_all(data)?;
+                w.write_all(&vec![0; mip_len - data.len()])?;
+            }
+            written = offset + len;
+        }
+        Ok(())
+    }
+
+    pub fn to_ktx2(&self) -> io::Result<Vec<u8>> {
+        let mut out = vec![];
+        self.write_ktx2(&mut out)?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    fn read_u32(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    fn read_u64(data: &[u8], at: usize) -> usize {
+        u64::from_le_bytes(data[at..at + 8].try_into().unwrap()) as usize
+    }
+
+    #[test]
+    fn write_ktx2() {
+        let input = include_bytes!("../assets/mikitm001_tex.txp");
+        let (_, atlas) = TextureAtlas::parse(input).unwrap();
+        let textures = atlas
+            .0
+            .iter()
+            .filter(|x| x.kind() == TextureKind::Mipmapped);
+        // Including the truncated smallest mips and the 2x0 one of texture 3
+        for tex in textures {
+            let out = tex.to_ktx2().unwrap();
+            let mips = &tex.subtextures[0].mipmaps;
+            assert_eq!(out[..12], IDENTIFIER);
+            let (vk_format, _, block, bytes, _, _) = mips[0].format.ktx2_layout().unwrap();
+            assert_eq!(read_u32(&out, 12), vk_format);
+            assert_eq!(read_u32(&out, 20), mips[0].width);
+            assert_eq!(read_u32(&out, 24), mips[0].height);
+            assert_eq!(read_u32(&out, 40) as usize, mips.len());
+            for (level, mip) in mips.iter().enumerate() {
+                let at = HEADER_SIZE + level * 24;
+                let (offset, len) = (read_u64(&out, at), read_u64(&out, at + 8));
+                let (w, h) = (
+                    (mips[0].width >> level).max(1),
+                    (mips[0].height >> level).max(1),
+                );
+                let blocks = |x: u32| x.div_ceil(block as u32) as usize;
+                assert_eq!(len, blocks(w) * blocks(h) * bytes as usize, "{}", mip);
+                let stored = mip.data.len().min(len);
+                assert_eq!(&out[offset..offset + stored], &mip.data[..stored]);
+            }
+            let (offset, len) = (read_u64(&out, HEADER_SIZE), read_u64(&out, HEADER_SIZE + 8));
+            assert_eq!(offset + len, out.len());
+        }
+    }
+
+    #[test]
+    fn unsupported_ktx2() {
+        let mip = Mipmap {
+            format: TextureFormat::RGB5,
+            width: 1,
+            height: 1,
+            data: vec![0; 2].into(),
+            ..Default::default()
+        };
+        let tex = Texture {
+            subtextures: vec![Subtexture { mipmaps: vec![mip] }],
+        };
+        let err = tex.to_ktx2().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
@@ -2,18 +2,50 @@ use std::borrow::Cow;
 
 #[cfg(feature = "pyo3")]
 use pyo3::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
 
+#[cfg(feature = "batch")]
+pub mod batch;
+#[cfg(feature = "capi")]
+pub mod c_api;
 #[cfg(feature = "ddsfile")]
 mod dds;
+mod decode;
+#[cfg(feature = "farc")]
+pub mod farc;
 #[cfg(feature = "image")]
 mod image;
 mod r#impl;
+mod index;
+mod ktx2;
+#[cfg(feature = "serde")]
+pub mod manifest;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "napi")]
+pub mod node_ffi;
+pub mod obj;
 #[cfg(feature = "pyo3")]
 pub mod py_ffi;
 mod read;
+#[cfg(feature = "serde")]
+pub mod report;
+mod stream;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod write;
 #[cfg(feature = "dcv-color-primitives")]
 mod yuv;
 
+pub use index::{MipmapHeader, SubtextureIndex, TextureAtlasIndex, TextureIndex};
+#[cfg(feature = "mmap")]
+pub use mmap::MappedAtlas;
+pub use read::error_offset;
+pub use stream::AtlasReader;
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct TextureAtlas<'a>(pub Vec<Texture<'a>>);
 
@@ -29,16 +61,43 @@ pub struct Subtexture<'a> {
 
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct Mipmap<'a> {
-    id: u32,
+    /// Index of this mip level inside of its subtexture, `0` being the largest.
+    ///
+    /// Array textures (e.g. cubemaps) may instead number their mips across the whole array,
+    /// as `slice * mip_count + level`; both are accepted when writing.
+    pub id: u32,
     pub width: u32,
     pub height: u32,
     pub format: TextureFormat,
     pub data: Cow<'a, [u8]>,
 }
 
+/// How the subtextures and mips of a [`Texture`] are meant to be used, see [`Texture::kind`]
+#[repr(u32)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "pyo3", pyclass(module = "txp"))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum TextureKind {
+    /// A single 2D image
+    Plain,
+    /// A single 2D image with its smaller mips
+    Mipmapped,
+    /// 6 subtextures, one per face
+    Cubemap,
+    /// Any other amount of subtextures
+    Array,
+    /// Luma in the first ATI2 mip and chroma in the second, they aren't actual mips
+    Yuv,
+}
+
 #[non_exhaustive]
-#[derive(Debug, Default, PartialEq, Clone, Copy)]
-#[cfg_attr(feature = "pyo3", pyclass)]
+#[repr(u32)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "pyo3", pyclass(module = "txp"))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TextureFormat {
     A8 = 0,
     #[default]
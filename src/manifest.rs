@@ -0,0 +1,431 @@
+//! Text manifests describing the layout of an atlas, with the mip data kept in separate files.
+//!
+//! The manifest types are plain serde types, so any format works (JSON, RON, TOML...).
+//! Paths are relative to the directory the data is saved to:
+//! - `.bin` files hold the raw mip data, exactly as stored in the atlas
+//! - anything else is an image which gets encoded to the format of the mip (requires `image`).
+//!   Images are stored upright, which is flipped vertically compared to the atlas.
+//!   Images larger than the mip are downscaled, so every mip of a subtexture can share one image.
+//!
+//! [`unpack`] and [`repack`] extract an atlas to a directory with a `manifest.json` and back.
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use super::*;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct AtlasManifest {
+    pub textures: Vec<TextureManifest>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct TextureManifest {
+    pub subtextures: Vec<SubtextureManifest>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct SubtextureManifest {
+    pub mipmaps: Vec<MipmapManifest>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct MipmapManifest {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub path: PathBuf,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl AtlasManifest {
+    /// Describes `atlas`, naming the data of every mip `tex{i}_sub{j}_mip{k}.bin`
+    pub fn new(atlas: &TextureAtlas<'_>) -> Self {
+        Self::with_paths(atlas, |i, j, k, _| {
+            PathBuf::from(format!("tex{}_sub{}_mip{}.bin", i, j, k))
+        })
+    }
+
+    /// Describes `atlas`, naming the data of every mip with `path(texture, subtexture, mip, &mipmap)`
+    pub fn with_paths<F>(atlas: &TextureAtlas<'_>, mut path: F) -> Self
+    where
+        F: FnMut(usize, usize, usize, &Mipmap<'_>) -> PathBuf,
+    {
+        let textures = atlas
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, tex)| TextureManifest {
+                subtextures: tex
+                    .subtextures
+                    .iter()
+                    .enumerate()
+                    .map(|(j, sub)| SubtextureManifest {
+                        mipmaps: sub
+                            .mipmaps
+                            .iter()
+                            .enumerate()
+                            .map(|(k, mip)| MipmapManifest {
+                                id: mip.id,
+                                width: mip.width,
+                                height: mip.height,
+                                format: mip.format,
+                                path: path(i, j, k, mip),
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self { textures }
+    }
+
+    /// Every mip with its texture and subtexture index
+    pub fn mipmaps(&self) -> impl Iterator<Item = (usize, usize, &MipmapManifest)> {
+        self.textures.iter().enumerate().flat_map(|(i, tex)| {
+            tex.subtextures
+                .iter()
+                .enumerate()
+                .flat_map(move |(j, sub)| sub.mipmaps.iter().map(move |mip| (i, j, mip)))
+        })
+    }
+
+    /// Writes the raw data of every mip of `atlas` to the `.bin` paths inside of `dir`
+    #[tracing::instrument(skip(self, atlas, dir))]
+    pub fn save_data<P: AsRef<Path>>(&self, atlas: &TextureAtlas<'_>, dir: P) -> io::Result<()> {
+        let dir = dir.as_ref();
+        let mips: Vec<_> = atlas
+            .0
+            .iter()
+            .flat_map(|x| x.subtextures.iter().flat_map(|x| x.mipmaps.iter()))
+            .collect();
+        let count = mips.len();
+        if count != self.mipmaps().count() {
+            return Err(invalid("the manifest doesn't describe the atlas".into()));
+        }
+        for ((_, _, entry), mip) in self.mipmaps().zip(mips) {
+            if is_raw(&entry.path) {
+                std::fs::write(join(dir, &entry.path)?, &mip.data)?;
+            }
+        }
+        debug!(count);
+        Ok(())
+    }
+
+    /// Rebuilds the atlas from the files inside of `dir`
+    #[tracing::instrument(skip(self, dir))]
+    pub fn load<P: AsRef<Path>>(&self, dir: P) -> io::Result<TextureAtlas<'static>> {
+        let dir = dir.as_ref();
+        let mut textures = vec![];
+        for tex in &self.textures {
+            let mut subtextures = vec![];
+            for sub in &tex.subtextures {
+                let mipmaps = sub
+                    .mipmaps
+                    .iter()
+                    .map(|x| x.load(dir))
+                    .collect::<io::Result<_>>()?;
+                subtextures.push(Subtexture { mipmaps });
+            }
+            textures.push(Texture { subtextures });
+        }
+        Ok(TextureAtlas(textures))
+    }
+}
+
+/// Joins a path from a manifest to `dir`, rejecting absolute paths and `..` leaving it
+fn join(dir: &Path, path: &Path) -> io::Result<PathBuf> {
+    use std::path::Component;
+    let relative = path
+        .components()
+        .all(|x| matches!(x, Component::Normal(_) | Component::CurDir));
+    match relative {
+        true => Ok(dir.join(path)),
+        false => Err(invalid(format!("{} leaves the directory", path.display()))),
+    }
+}
+
+fn is_raw(path: &Path) -> bool {
+    path.extension().is_some_and(|x| x == "bin")
+}
+
+impl MipmapManifest {
+    pub fn load<P: AsRef<Path>>(&self, dir: P) -> io::Result<Mipmap<'static>> {
+        let path = join(dir.as_ref(), &self.path)?;
+        let data = if is_raw(&path) {
+            std::fs::read(&path)?
+        } else {
+            self.encode(&path)?
+        };
+        Ok(Mipmap {
+            id: self.id,
+            width: self.width,
+            height: self.height,
+            format: self.format,
+            data: data.into(),
+        })
+    }
+
+    #[cfg(feature = "image")]
+    fn encode(&self, path: &Path) -> io::Result<Vec<u8>> {
+        use ::image::GenericImageView;
+        let image =
+            ::image::open(path).map_err(|e| invalid(format!("{}: {}", path.display(), e)))?;
+        let (width, height) = image.dimensions();
+        let image = if (width, height) == (self.width, self.height) {
+            image
+        } else if width >= self.width && height >= self.height {
+            // the smallest DXT mips can be 0 pixels high
+            image.resize_exact(
+                self.width.max(1),
+                self.height.max(1),
+                ::image::imageops::FilterType::Triangle,
+            )
+        } else {
+            return Err(invalid(format!(
+                "{} is {}x{}, expected {}x{}",
+                path.display(),
+                image.width(),
+                image.height(),
+                self.width,
+                self.height
+            )));
+        };
+        let mip = Mipmap::from_image(&image.flipv(), self.format).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("cannot encode {:?}", self.format),
+            )
+        })?;
+        Ok(mip.data.into_owned())
+    }
+
+    #[cfg(not(feature = "image"))]
+    fn encode(&self, path: &Path) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} is an image, enable the `image` feature", path.display()),
+        ))
+    }
+}
+
+const MANIFEST: &str = "manifest.json";
+
+/// Extracts `atlas` to `dir` along with a `manifest.json` describing it.
+///
+/// Subtextures the `image` feature can decode are saved upright as a single PNG of their first mip,
+/// `tex{i}.png` or `tex{i}_sub{j}.png` for arrays. Everything else, including [`TextureKind::Yuv`]
+/// textures, is kept as raw `.bin` data.
+#[tracing::instrument(skip(atlas, dir), fields(dir = %dir.as_ref().display()))]
+pub fn unpack<P: AsRef<Path>>(atlas: &TextureAtlas<'_>, dir: P) -> io::Result<AtlasManifest> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    let mut images = std::collections::HashMap::new();
+    for (i, tex) in atlas.0.iter().enumerate() {
+        // the second mip of YUV textures isn't a smaller copy, keep them raw
+        if tex.kind() == TextureKind::Yuv {
+            continue;
+        }
+        for (j, sub) in tex.subtextures.iter().enumerate() {
+            let name = match tex.subtextures.len() {
+                1 => format!("tex{}.png", i),
+                _ => format!("tex{}_sub{}.png", i, j),
+            };
+            if let Some(mip) = sub.mipmaps.first() {
+                if save_image(mip, &dir.join(&name))? {
+                    images.insert((i, j), PathBuf::from(name));
+                }
+            }
+        }
+    }
+    debug!(images = images.len());
+    let manifest = AtlasManifest::with_paths(atlas, |i, j, k, _| {
+        images
+            .get(&(i, j))
+            .cloned()
+            .unwrap_or_else(|| format!("tex{}_sub{}_mip{}.bin", i, j, k).into())
+    });
+    manifest.save_data(atlas, dir)?;
+    let json = serde_json::to_vec_pretty(&manifest).map_err(io::Error::from)?;
+    std::fs::write(dir.join(MANIFEST), json)?;
+    Ok(manifest)
+}
+
+/// Rebuilds an atlas extracted with [`unpack`], re-encoding images to the format and mip count
+/// recorded in the manifest
+///
+/// ```no_run
+/// let atlas = txp::manifest::repack("mikitm001")?;
+/// std::fs::write("mikitm001_tex.bin", atlas.try_to_bytes()?)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[tracing::instrument(skip(dir), fields(dir = %dir.as_ref().display()))]
+pub fn repack<P: AsRef<Path>>(dir: P) -> io::Result<TextureAtlas<'static>> {
+    let dir = dir.as_ref();
+    let json = std::fs::read(dir.join(MANIFEST))?;
+    let manifest: AtlasManifest = serde_json::from_slice(&json).map_err(io::Error::from)?;
+    manifest.load(dir)
+}
+
+#[cfg(feature = "image")]
+fn save_image(mip: &Mipmap<'_>, path: &Path) -> io::Result<bool> {
+    // only extract what can be encoded back
+    if !mip.format.can_encode() {
+        return Ok(false);
+    }
+    let image = match mip.clone().to_dynamic_image() {
+        Some(image) => image,
+        None => return Ok(false),
+    };
+    image
+        .flipv()
+        .save(path)
+        .map_err(|e| invalid(format!("{}: {}", path.display(), e)))?;
+    Ok(true)
+}
+
+#[cfg(not(feature = "image"))]
+fn save_image(_: &Mipmap<'_>, _: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &[u8] = include_bytes!("../assets/mikitm001_tex.txp");
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn serialize_formats() {
+        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        let manifest = AtlasManifest::new(&atlas);
+        let json = serde_json::to_string_pretty(&manifest).unwrap();
+        assert!(json.contains("\"format\": \"DXT5\""));
+        assert_eq!(manifest, serde_json::from_str(&json).unwrap());
+        let ron = ron::to_string(&manifest).unwrap();
+        assert_eq!(manifest, ron::from_str(&ron).unwrap());
+        let toml = toml::to_string(&manifest).unwrap();
+        assert_eq!(manifest, toml::from_str(&toml).unwrap());
+    }
+
+    #[test]
+    fn roundtrip_raw() {
+        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        let dir = temp_dir("txp_manifest_raw");
+        let manifest = AtlasManifest::new(&atlas);
+        manifest.save_data(&atlas, &dir).unwrap();
+        assert!(dir.join("tex1_sub0_mip8.bin").exists());
+        assert_eq!(manifest.load(&dir).unwrap(), atlas);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reject_escaping_paths() {
+        let dir = temp_dir("txp_manifest_escape");
+        for path in ["../tex.bin", "/tmp/tex.bin", "a/../../tex.bin"] {
+            let mip = MipmapManifest {
+                path: path.into(),
+                ..Default::default()
+            };
+            let err = mip.load(&dir).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}", path);
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn load_image() {
+        let dir = temp_dir("txp_manifest_image");
+        let image = ::image::RgbaImage::from_fn(8, 4, |x, y| {
+            ::image::Rgba([x as u8 * 30, y as u8 * 60, 0, 255])
+        });
+        image.save(dir.join("tex.png")).unwrap();
+        let mip = |format| MipmapManifest {
+            id: 0,
+            width: 8,
+            height: 4,
+            format,
+            path: "tex.png".into(),
+        };
+        let manifest = AtlasManifest {
+            textures: vec![TextureManifest {
+                subtextures: vec![SubtextureManifest {
+                    mipmaps: vec![mip(TextureFormat::RGBA8)],
+                }],
+            }],
+        };
+        let atlas = manifest.load(&dir).unwrap();
+        let data = &atlas.0[0].subtextures[0].mipmaps[0].data;
+        // flipped vertically
+        assert_eq!(&data[..4], &[0, 180, 0, 255]);
+        let dxt = MipmapManifest {
+            format: TextureFormat::DXT5,
+            ..mip(TextureFormat::DXT5)
+        };
+        assert_eq!(dxt.load(&dir).unwrap().data.len(), 32);
+        let smaller = MipmapManifest {
+            width: 4,
+            height: 2,
+            ..dxt.clone()
+        };
+        assert_eq!(smaller.load(&dir).unwrap().data.len(), 16);
+        let wrong = MipmapManifest { width: 16, ..dxt };
+        assert_eq!(
+            wrong.load(&dir).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unpack_repack() {
+        let (_, mut atlas) = TextureAtlas::parse(INPUT).unwrap();
+        // re-encoding is slow in debug builds
+        atlas.0.truncate(3);
+        let dir = temp_dir("txp_unpack");
+        let manifest = unpack(&atlas, &dir).unwrap();
+        assert!(dir.join(MANIFEST).exists());
+        let repacked = repack(&dir).unwrap();
+        let bytes = repacked.try_to_bytes().unwrap();
+        let (_, reparsed) = TextureAtlas::parse(&bytes).unwrap();
+        assert_eq!(reparsed.0.len(), atlas.0.len());
+        for ((tex, new), entry) in atlas.0.iter().zip(&reparsed.0).zip(&manifest.textures) {
+            for ((sub, new), entry) in tex
+                .subtextures
+                .iter()
+                .zip(&new.subtextures)
+                .zip(&entry.subtextures)
+            {
+                assert_eq!(sub.mipmaps.len(), new.mipmaps.len());
+                for ((mip, new), entry) in sub.mipmaps.iter().zip(&new.mipmaps).zip(&entry.mipmaps)
+                {
+                    assert_eq!(
+                        (mip.id, mip.width, mip.height),
+                        (new.id, new.width, new.height)
+                    );
+                    assert_eq!(mip.format, new.format);
+                    if is_raw(&entry.path) {
+                        assert_eq!(mip.data, new.data);
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "image")]
+        assert!(dir.join("tex0.png").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
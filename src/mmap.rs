@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use super::*;
+
+/// A memory mapped `_tex.bin`, atlases borrowed from it don't copy any texture data
+#[derive(Debug)]
+pub struct MappedAtlas {
+    mmap: Mmap,
+}
+
+impl TextureAtlas<'_> {
+    /// Memory maps the file at `path` and checks that it contains a valid atlas.
+    ///
+    /// As with any memory map, the file must not be modified while the handle is alive.
+    ///
+    /// ```no_run
+    /// let file = txp::TextureAtlas::open("mikitm001_tex.bin")?;
+    /// for tex in file.atlas().0 {
+    ///     println!("{} subtexture(s)", tex.subtextures.len());
+    /// }
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[tracing::instrument(skip(path), fields(path = %path.as_ref().display()))]
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedAtlas> {
+        let mmap = map(path)?;
+        TextureAtlas::parse(&mmap)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(MappedAtlas { mmap })
+    }
+}
+
+/// Maps the file without checking its contents, for callers reporting their own parse errors
+pub(crate) fn map<P: AsRef<Path>>(path: P) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    // SAFETY: the mapping is read only and documented to require an unmodified file
+    unsafe { Mmap::map(&file) }
+}
+
+impl MappedAtlas {
+    /// Parses the atlas borrowing from the mapping, this only walks the offset tables
+    pub fn atlas(&self) -> TextureAtlas<'_> {
+        let (_, atlas) = TextureAtlas::parse(&self.mmap).expect("validated in `open`");
+        atlas
+    }
+
+    /// Reads only the layout of the atlas, see [`TextureAtlasIndex`]
+    pub fn index(&self) -> TextureAtlasIndex {
+        let (_, index) = TextureAtlasIndex::parse(&self.mmap).expect("validated in `open`");
+        index
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.mmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &[u8] = include_bytes!("../assets/mikitm001_tex.txp");
+
+    #[test]
+    fn open_atlas() {
+        let file = TextureAtlas::open("assets/mikitm001_tex.txp").unwrap();
+        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        assert_eq!(file.atlas(), atlas);
+        assert!(matches!(
+            file.atlas().0[0].subtextures[0].mipmaps[0].data,
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn open_invalid() {
+        let err = TextureAtlas::open("Cargo.toml").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
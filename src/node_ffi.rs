@@ -0,0 +1,233 @@
+//! Node.js bindings through N-API, mirroring [`py_ffi`](crate) with camelCase names.
+//!
+//! Formats and kinds are the names of their variants, e.g. `"DXT5"` and `"Yuv"`.
+//!
+//! ```js
+//! const txp = require("./txp.node");
+//! const atlas = txp.read("mikitm001_tex.bin");
+//! for (const tex of atlas.textures) {
+//!     tex.savePng(`${tex.kind}.png`);
+//! }
+//! ```
+use std::io;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+#[napi]
+pub struct TextureAtlas(crate::TextureAtlas<'static>);
+
+#[napi]
+pub struct Texture(crate::Texture<'static>);
+
+#[napi]
+pub struct Subtexture(crate::Subtexture<'static>);
+
+#[napi]
+pub struct Mipmap(crate::Mipmap<'static>);
+
+fn wrap<T: Clone, J: From<T>>(items: &[T]) -> Vec<J> {
+    items.iter().cloned().map(Into::into).collect()
+}
+
+impl From<crate::Texture<'static>> for Texture {
+    fn from(tex: crate::Texture<'static>) -> Self {
+        Self(tex)
+    }
+}
+
+impl From<crate::Subtexture<'static>> for Subtexture {
+    fn from(sub: crate::Subtexture<'static>) -> Self {
+        Self(sub)
+    }
+}
+
+impl From<crate::Mipmap<'static>> for Mipmap {
+    fn from(mip: crate::Mipmap<'static>) -> Self {
+        Self(mip)
+    }
+}
+
+/// Invalid or unsupported textures are the caller's fault, anything else is a generic failure
+fn write_error(err: io::Error) -> Error {
+    let status = match err.kind() {
+        io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData | io::ErrorKind::Unsupported => {
+            Status::InvalidArg
+        }
+        _ => Status::GenericFailure,
+    };
+    Error::new(status, err.to_string())
+}
+
+fn cannot_decode(what: impl std::fmt::Debug) -> Error {
+    Error::new(Status::InvalidArg, format!("cannot decode {:?}", what))
+}
+
+/// Saves rows top-down, flipped from the stored order like every other export
+fn save_image(image: &::image::DynamicImage, path: &str, format: Option<&str>) -> Result<()> {
+    use ::image::{ImageError, ImageFormat};
+    let format = match format {
+        Some(ext) => ImageFormat::from_extension(ext),
+        None => ImageFormat::from_path(path).ok(),
+    };
+    let format = format.ok_or_else(|| Error::new(Status::InvalidArg, "unknown image format"))?;
+    image
+        .flipv()
+        .save_with_format(path, format)
+        .map_err(|err| match err {
+            ImageError::IoError(err) => write_error(err),
+            err => Error::new(Status::InvalidArg, err.to_string()),
+        })
+}
+
+#[napi]
+impl TextureAtlas {
+    #[napi(getter)]
+    pub fn textures(&self) -> Vec<Texture> {
+        wrap(&self.0 .0)
+    }
+
+    /// Serializes the atlas to the contents of a `_tex.bin`
+    #[napi]
+    pub fn to_bytes(&self) -> Result<Buffer> {
+        Ok(self.0.try_to_bytes().map_err(write_error)?.into())
+    }
+
+    #[napi]
+    pub fn write(&self, path: String) -> Result<()> {
+        let bytes = self.0.try_to_bytes().map_err(write_error)?;
+        std::fs::write(path, bytes).map_err(write_error)
+    }
+}
+
+#[napi]
+impl Texture {
+    #[napi(getter)]
+    pub fn subtextures(&self) -> Vec<Subtexture> {
+        wrap(&self.0.subtextures)
+    }
+
+    #[napi(getter)]
+    pub fn kind(&self) -> String {
+        format!("{:?}", self.0.kind())
+    }
+
+    #[napi]
+    pub fn to_dds_bytes(&self) -> Result<Buffer> {
+        let dds = self
+            .0
+            .to_dds()
+            .map_err(|e| Error::from_reason(e.to_string()))?;
+        let mut vec = vec![];
+        dds.write(&mut vec)
+            .map_err(|e| Error::from_reason(e.to_string()))?;
+        Ok(vec.into())
+    }
+
+    /// Saves the image of a subtexture as displayed, YUV textures are converted to RGBA
+    #[napi]
+    pub fn save_png(&self, path: String, subtexture: Option<u32>) -> Result<()> {
+        let subtexture = subtexture.unwrap_or(0) as usize;
+        let images = self.0.to_images();
+        let image = images
+            .get(subtexture)
+            .ok_or_else(|| Error::new(Status::InvalidArg, "subtexture index out of range"))?
+            .as_ref()
+            .ok_or_else(|| cannot_decode(self.0.kind()))?;
+        save_image(image, &path, Some("png"))
+    }
+
+    /// Writes a DDS container with every subtexture and mip as stored, with a DX10 header for
+    /// formats lacking a legacy D3D one such as BC4 to BC7. Throws if ddsfile rejects the layout
+    #[napi]
+    pub fn save_dds(&self, path: String) -> Result<()> {
+        let bytes = self.to_dds_bytes()?;
+        std::fs::write(path, bytes).map_err(write_error)
+    }
+
+    /// Writes a KTX2 container with every subtexture and mip as stored. Throws for the packed
+    /// 16-bit formats RGB5, RGB5A1 and RGBA4, mixed formats or uneven mip counts
+    #[napi]
+    pub fn save_ktx2(&self, path: String) -> Result<()> {
+        let ktx2 = self.0.to_ktx2().map_err(write_error)?;
+        std::fs::write(path, ktx2).map_err(write_error)
+    }
+}
+
+#[napi]
+impl Subtexture {
+    #[napi(getter)]
+    pub fn mipmaps(&self) -> Vec<Mipmap> {
+        wrap(&self.0.mipmaps)
+    }
+
+    /// RGBA pixels of the largest mip, or of both mips combined when they're YUV
+    #[napi]
+    pub fn to_rgba(&self) -> Result<Buffer> {
+        Ok(self.0.decode_rgba8().map_err(write_error)?.into())
+    }
+}
+
+#[napi]
+impl Mipmap {
+    /// Index of the mip level inside of its subtexture, see [`Mipmap::id`]
+    #[napi(getter)]
+    pub fn id(&self) -> u32 {
+        self.0.id
+    }
+    #[napi(getter)]
+    pub fn width(&self) -> u32 {
+        self.0.width
+    }
+    #[napi(getter)]
+    pub fn height(&self) -> u32 {
+        self.0.height
+    }
+    #[napi(getter)]
+    pub fn format(&self) -> String {
+        format!("{:?}", self.0.format)
+    }
+    #[napi(getter)]
+    pub fn data(&self) -> Buffer {
+        self.0.data.to_vec().into()
+    }
+
+    #[napi]
+    pub fn to_rgba(&self) -> Result<Buffer> {
+        Ok(self.0.decode_rgba8().map_err(write_error)?.into())
+    }
+
+    /// Decodes and saves the mip, `format` is an extension like `"png"`, by default the one of `path`
+    #[napi]
+    pub fn save(&self, path: String, format: Option<String>) -> Result<()> {
+        use ::image::{DynamicImage, ImageBuffer};
+        let mip = &self.0;
+        let rgba = mip.decode_rgba8().map_err(write_error)?;
+        let image = ImageBuffer::from_raw(mip.width, mip.height, rgba)
+            .ok_or_else(|| cannot_decode(mip.format))?;
+        save_image(&DynamicImage::ImageRgba8(image), &path, format.as_deref())
+    }
+}
+
+fn parse(data: &[u8]) -> Result<TextureAtlas> {
+    let (_, atlas) = crate::TextureAtlas::parse(data).map_err(|e| {
+        let msg = match crate::error_offset(data, &e) {
+            Some(offset) => format!("invalid atlas at {:#x}", offset),
+            None => "truncated atlas".to_string(),
+        };
+        Error::new(Status::InvalidArg, msg)
+    })?;
+    Ok(TextureAtlas(atlas.into_owned()))
+}
+
+#[napi]
+pub fn read(path: String) -> Result<TextureAtlas> {
+    let data = std::fs::read(path).map_err(write_error)?;
+    parse(&data)
+}
+
+/// Parses an atlas out of a `Buffer` or `Uint8Array`
+#[napi]
+pub fn loads(data: Uint8Array) -> Result<TextureAtlas> {
+    parse(&data)
+}
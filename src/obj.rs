@@ -0,0 +1,267 @@
+//! Minimal reader for classic object sets (`_obj.bin`), enough to find which textures a model uses.
+//!
+//! Materials don't reference the `id` of a [`Mipmap`], which is only its mip index.
+//! Instead every object set carries a table of texture IDs in the same order
+//! as the textures of its `_tex.bin`, and materials refer to entries of that table.
+use std::convert::TryInto;
+
+use nom::bytes::complete::{take, take_until};
+use nom::combinator::verify;
+use nom::multi::count;
+use nom::number::complete::u32;
+use nom::number::Endianness;
+use nom::IResult;
+use nom::Parser;
+use tracing::{debug, trace};
+
+use super::read::at_offset;
+use super::*;
+
+const MATERIAL_SIZE: usize = 0x4B0;
+const MATERIAL_TEXTURE_SIZE: usize = 0x78;
+const MATERIAL_TEXTURES: usize = 8;
+const MATERIAL_NAME: usize = 0x430;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ObjectSet {
+    pub objects: Vec<Object>,
+    /// IDs of the textures in the accompanying `_tex.bin`, in atlas order
+    pub texture_ids: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Object {
+    pub name: String,
+    pub id: u32,
+    pub materials: Vec<Material>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Material {
+    pub name: String,
+    /// IDs of the textures bound to this material, unused slots are skipped
+    pub texture_ids: Vec<u32>,
+}
+
+/// How the textures of an atlas are used by the materials of an object set
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct TextureBindings {
+    /// Atlas indices of the textures referenced by at least one material
+    pub used: Vec<usize>,
+    /// Atlas indices of the textures no material references
+    pub unused: Vec<usize>,
+    /// Texture IDs referenced by a material that have no texture in the atlas
+    pub missing: Vec<u32>,
+}
+
+fn parse_magic(i: &[u8]) -> IResult<&[u8], Endianness> {
+    use nom::number::Endianness::*;
+    let valid = |x: &u32| *x == 0x05062500 || *x == 0x05062501;
+    if let Ok((i, _)) = verify(u32::<_, nom::error::Error<_>>(Little), valid)(i) {
+        return Ok((i, Little));
+    }
+    let (i, _) = verify(u32(Big), valid)(i)?;
+    Ok((i, Big))
+}
+
+fn string(i: &[u8]) -> IResult<&[u8], String> {
+    let (i, s) = take_until(&b"\0"[..])(i)?;
+    Ok((i, String::from_utf8_lossy(s).into_owned()))
+}
+
+fn table<'a, O>(
+    i0: &'a [u8],
+    offset: u32,
+    cnt: usize,
+    f: impl Parser<&'a [u8], O, nom::error::Error<&'a [u8]>>,
+) -> IResult<&'a [u8], Vec<O>> {
+    at_offset(offset as usize, count(f, cnt)).parse(i0)
+}
+
+impl ObjectSet {
+    #[tracing::instrument(name = "obj_set", skip(i0))]
+    pub fn parse(i0: &[u8]) -> IResult<&[u8], ObjectSet> {
+        let (i, endian) = parse_magic(i0)?;
+        let (i, object_count) = u32(endian)(i)?;
+        let (i, _bone_count) = u32(endian)(i)?;
+        let (i, objects_offset) = u32(endian)(i)?;
+        let (i, _skins_offset) = u32(endian)(i)?;
+        let (i, names_offset) = u32(endian)(i)?;
+        let (i, ids_offset) = u32(endian)(i)?;
+        let (i, texture_ids_offset) = u32(endian)(i)?;
+        let (i, texture_id_count) = u32(endian)(i)?;
+        debug!(?endian, object_count, texture_id_count);
+        let object_count: usize = object_count.try_into().unwrap();
+
+        let (_, offsets) = table(i0, objects_offset, object_count, u32(endian))?;
+        let (_, names) = table(i0, names_offset, object_count, u32(endian))?;
+        let (_, ids) = table(i0, ids_offset, object_count, u32(endian))?;
+        let (_, texture_ids) = table(
+            i0,
+            texture_ids_offset,
+            texture_id_count.try_into().unwrap(),
+            u32(endian),
+        )?;
+        let mut objects = vec![];
+        for ((offset, name), id) in offsets.into_iter().zip(names).zip(ids) {
+            let (_, name) = at_offset(name as usize, string).parse(i0)?;
+            let (_, materials) =
+                at_offset(offset as usize, |i| Object::parse_materials(i, endian)).parse(i0)?;
+            trace!(name, id, materials = materials.len());
+            objects.push(Object {
+                name,
+                id,
+                materials,
+            });
+        }
+        Ok((
+            i,
+            Self {
+                objects,
+                texture_ids,
+            },
+        ))
+    }
+
+    /// Position of a texture ID in the accompanying atlas
+    pub fn texture_index(&self, id: u32) -> Option<usize> {
+        self.texture_ids.iter().position(|&x| x == id)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Object> {
+        self.objects.iter().find(|x| x.name == name)
+    }
+
+    /// Joins the materials of every object against `atlas`
+    pub fn bindings(&self, atlas: &TextureAtlas<'_>) -> TextureBindings {
+        self.bind(self.objects.iter().flat_map(Object::texture_ids), atlas)
+    }
+
+    /// Joins the materials of a single object against `atlas`
+    pub fn object_bindings(&self, object: &Object, atlas: &TextureAtlas<'_>) -> TextureBindings {
+        self.bind(object.texture_ids(), atlas)
+    }
+
+    fn bind(&self, ids: impl Iterator<Item = u32>, atlas: &TextureAtlas<'_>) -> TextureBindings {
+        let mut bindings = TextureBindings::default();
+        for id in ids {
+            match self.texture_index(id).filter(|&x| x < atlas.0.len()) {
+                Some(index) if !bindings.used.contains(&index) => bindings.used.push(index),
+                Some(_) => (),
+                None if !bindings.missing.contains(&id) => bindings.missing.push(id),
+                None => (),
+            }
+        }
+        bindings.used.sort_unstable();
+        bindings.unused = (0..atlas.0.len())
+            .filter(|x| !bindings.used.contains(x))
+            .collect();
+        bindings
+    }
+}
+
+impl Object {
+    /// Offsets inside of an object are relative to its start
+    fn parse_materials(i0: &[u8], endian: Endianness) -> IResult<&[u8], Vec<Material>> {
+        // version, flags and bounding sphere
+        let (i, _) = take(0x18usize)(i0)?;
+        let (i, _mesh_count) = u32(endian)(i)?;
+        let (i, _meshes_offset) = u32(endian)(i)?;
+        let (i, material_count) = u32(endian)(i)?;
+        let (i, materials_offset) = u32(endian)(i)?;
+        let mut materials = vec![];
+        for j in 0..material_count as usize {
+            let offset = materials_offset as usize + j * MATERIAL_SIZE;
+            let (_, material) = at_offset(offset, |i| Material::parse(i, endian)).parse(i0)?;
+            materials.push(material);
+        }
+        Ok((i, materials))
+    }
+
+    /// Every texture ID bound by the materials of this object
+    pub fn texture_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.materials
+            .iter()
+            .flat_map(|x| x.texture_ids.iter().copied())
+    }
+}
+
+impl Material {
+    fn parse(i0: &[u8], endian: Endianness) -> IResult<&[u8], Material> {
+        let mut texture_ids = vec![];
+        for k in 0..MATERIAL_TEXTURES {
+            let offset = 0x14 + k * MATERIAL_TEXTURE_SIZE + 4;
+            let (_, id) = at_offset(offset, u32(endian)).parse(i0)?;
+            if id != u32::MAX {
+                texture_ids.push(id);
+            }
+        }
+        let (_, name) = at_offset(MATERIAL_NAME, string).parse(i0)?;
+        let (i, _) = take(MATERIAL_SIZE)(i0)?;
+        Ok((i, Self { name, texture_ids }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &[u8] = include_bytes!("../assets/mikitm001_tex.txp");
+
+    fn put(out: &mut [u8], offset: usize, x: u32) {
+        out[offset..offset + 4].copy_from_slice(&x.to_le_bytes());
+    }
+
+    /// An object set with a single object and material binding `ids`
+    fn object_set(texture_ids: &[u32], ids: &[u32]) -> Vec<u8> {
+        const OBJ: usize = 0x100;
+        let mut out = vec![0; OBJ + 0x50 + MATERIAL_SIZE];
+        put(&mut out, 0x00, 0x05062500);
+        put(&mut out, 0x04, 1);
+        put(&mut out, 0x0C, 0x40);
+        put(&mut out, 0x14, 0x44);
+        put(&mut out, 0x18, 0x48);
+        put(&mut out, 0x1C, 0x60);
+        put(&mut out, 0x20, texture_ids.len() as u32);
+        put(&mut out, 0x40, OBJ as u32);
+        put(&mut out, 0x44, 0x50);
+        out[0x50..0x5C].copy_from_slice(b"MIKITM001\0\0\0");
+        put(&mut out, 0x48, 42);
+        for (j, id) in texture_ids.iter().enumerate() {
+            put(&mut out, 0x60 + j * 4, *id);
+        }
+        put(&mut out, OBJ + 0x20, 1);
+        put(&mut out, OBJ + 0x24, 0x50);
+        let mat = OBJ + 0x50;
+        for k in 0..MATERIAL_TEXTURES {
+            let id = ids.get(k).copied().unwrap_or(u32::MAX);
+            put(&mut out, mat + 0x14 + k * MATERIAL_TEXTURE_SIZE + 4, id);
+        }
+        out[mat + MATERIAL_NAME..mat + MATERIAL_NAME + 5].copy_from_slice(b"skin\0");
+        out
+    }
+
+    #[test]
+    fn read_object_set() {
+        let input = object_set(&[100, 200, 300], &[200, 300]);
+        let (_, set) = ObjectSet::parse(&input).unwrap();
+        assert_eq!(set.texture_ids, [100, 200, 300]);
+        let obj = set.get("MIKITM001").unwrap();
+        assert_eq!(obj.id, 42);
+        assert_eq!(obj.materials[0].name, "skin");
+        assert_eq!(obj.texture_ids().collect::<Vec<_>>(), [200, 300]);
+    }
+
+    #[test]
+    fn bindings() {
+        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        let input = object_set(&[100, 200, 300], &[300, 200, 300, 999]);
+        let (_, set) = ObjectSet::parse(&input).unwrap();
+        let bindings = set.bindings(&atlas);
+        assert_eq!(bindings.used, [1, 2]);
+        assert_eq!(bindings.unused.len(), atlas.0.len() - 2);
+        assert_eq!(bindings.unused[0], 0);
+        assert_eq!(bindings.missing, [999]);
+        assert_eq!(bindings, set.object_bindings(&set.objects[0], &atlas));
+    }
+}
@@ -1,157 +1,462 @@
-use pyo3::exceptions::PyException;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
 use pyo3::prelude::*;
+use pyo3::pyclass::CompareOp;
+use pyo3::types::{PyBytes, PyTuple};
 use pyo3::wrap_pyfunction;
+use pyo3::PyTypeInfo;
 
 use super::*;
 
-#[pyclass]
+#[pyclass(module = "txp")]
 #[derive(Debug, PartialEq, Clone)]
-pub struct PyTextureAtlas {
-    #[pyo3(get, set)]
-    pub textures: Vec<PyTexture>,
-}
+pub struct PyTextureAtlas(pub TextureAtlas<'static>);
 
-#[pyclass]
+#[pyclass(module = "txp")]
 #[derive(Debug, PartialEq, Clone)]
-pub struct PyTexture {
-    #[pyo3(get, set)]
-    pub subtextures: Vec<PySubtexture>,
-}
+pub struct PyTexture(pub Texture<'static>);
 
-#[pyclass]
+#[pyclass(module = "txp")]
 #[derive(Debug, PartialEq, Clone)]
-pub struct PySubtexture {
-    #[pyo3(get, set)]
-    pub mipmaps: Vec<PyMipmap>,
-}
+pub struct PySubtexture(pub Subtexture<'static>);
 
-#[pyclass]
+#[pyclass(module = "txp")]
 #[derive(Debug, PartialEq, Clone)]
-pub struct PyMipmap {
-    id: u32,
-    #[pyo3(get, set)]
-    pub width: u32,
-    #[pyo3(get, set)]
-    pub height: u32,
-    #[pyo3(get, set)]
-    pub format: TextureFormat,
-    #[pyo3(get, set)]
-    pub data: Vec<u8>,
-}
+pub struct PyMipmap(pub Mipmap<'static>);
 
 #[pymethods]
 impl PyMipmap {
+    #[new]
+    #[args(id = "0")]
+    fn new(
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        data: &PyAny,
+        id: u32,
+    ) -> PyResult<Self> {
+        Ok(Self(Mipmap {
+            id,
+            width,
+            height,
+            format,
+            data: input_bytes(data)?.into(),
+        }))
+    }
+
+    /// Encodes pixels with 1 to 4 channels, e.g. RGBA, from any buffer such as a numpy array
     #[cfg(feature = "image")]
-    fn to_rgb(&self) -> Option<Vec<(u8, u8, u8)>> {
-        let sub: Mipmap<'_> = self.clone().into();
-        sub.to_dynamic_image().map(|x| {
-            x.to_rgb()
-                .pixels()
-                .map(|x| (x.0[0], x.0[1], x.0[2]))
-                .collect()
-        })
+    #[staticmethod]
+    fn from_pixels(
+        width: u32,
+        height: u32,
+        pixels: &PyAny,
+        format: TextureFormat,
+    ) -> PyResult<Self> {
+        let image = image_from_pixels(width, height, pixel_bytes(pixels)?)?;
+        Mipmap::from_image(&image, format)
+            .map(Self)
+            .ok_or_else(|| cannot_encode(format))
     }
+
+    /// Encodes the image at `path`, as is without flipping it
     #[cfg(feature = "image")]
+    #[staticmethod]
+    fn from_file(path: String, format: TextureFormat) -> PyResult<Self> {
+        let image = ::image::open(path).map_err(ExternalError)?;
+        Mipmap::from_image(&image, format)
+            .map(Self)
+            .ok_or_else(|| cannot_encode(format))
+    }
+
+    /// Index of the mip level inside of its subtexture, see [`Mipmap::id`]
+    #[getter]
+    fn id(&self) -> u32 {
+        self.0.id
+    }
+    #[setter]
+    fn set_id(&mut self, id: u32) {
+        self.0.id = id;
+    }
+    #[getter]
+    fn width(&self) -> u32 {
+        self.0.width
+    }
+    #[setter]
+    fn set_width(&mut self, width: u32) {
+        self.0.width = width;
+    }
+    #[getter]
+    fn height(&self) -> u32 {
+        self.0.height
+    }
+    #[setter]
+    fn set_height(&mut self, height: u32) {
+        self.0.height = height;
+    }
+    #[getter]
+    fn format(&self) -> TextureFormat {
+        self.0.format
+    }
+    #[setter]
+    fn set_format(&mut self, format: TextureFormat) {
+        self.0.format = format;
+    }
+    #[getter]
+    fn data(&self) -> Vec<u8> {
+        self.0.data.to_vec()
+    }
+    #[setter]
+    fn set_data(&mut self, data: &PyAny) -> PyResult<()> {
+        self.0.data = input_bytes(data)?.into();
+        Ok(())
+    }
+
+    fn to_rgb(&self) -> Option<Vec<(u8, u8, u8)>> {
+        let rgba = self.0.decode_rgba8().ok()?;
+        Some(rgba.chunks_exact(4).map(|x| (x[0], x[1], x[2])).collect())
+    }
     fn to_rgba(&self) -> Option<Vec<(u8, u8, u8, u8)>> {
-        let sub: Mipmap<'_> = self.clone().into();
-        sub.to_dynamic_image().map(|x| {
-            x.to_rgba()
-                .pixels()
-                .map(|x| (x.0[0], x.0[1], x.0[2], x.0[3]))
-                .collect()
-        })
+        self.0.decode_rgba8().ok().map(rgba_tuples)
+    }
+
+    /// Decoded pixels as a `memoryview` of shape `(height, width, channels)`, rows as stored.
+    ///
+    /// `numpy.asarray` wraps it without copying. `float32` gives values in `0.0..=1.0`,
+    /// except for BC6H whose HDR values are kept as decoded
+    #[args(channels = "4", float32 = "false")]
+    fn to_array(&self, py: Python<'_>, channels: u32, float32: bool) -> PyResult<Option<PyObject>> {
+        if !(1..=4).contains(&channels) {
+            return Err(PyValueError::new_err("channels must be between 1 and 4"));
+        }
+        let shape = (self.0.height, self.0.width, channels);
+        if float32 {
+            let rgba = match self.0.decode_rgba32f() {
+                Ok(rgba) => rgba,
+                Err(_) => return Ok(None),
+            };
+            let luma = |p: &[f32]| 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2];
+            let pixels = select_channels(rgba, channels, luma);
+            let bytes: Vec<u8> = pixels.iter().flat_map(|x| x.to_ne_bytes()).collect();
+            return pixel_view(py, &bytes, "f", shape).map(Some);
+        }
+        let rgba = match self.0.decode_rgba8() {
+            Ok(rgba) => rgba,
+            Err(_) => return Ok(None),
+        };
+        let luma = |p: &[u8]| {
+            (0.2126 * p[0] as f32 + 0.7152 * p[1] as f32 + 0.0722 * p[2] as f32).round() as u8
+        };
+        let pixels = select_channels(rgba, channels, luma);
+        pixel_view(py, &pixels, "B", shape).map(Some)
+    }
+
+    /// Decodes and saves the mip, `format` is an extension like `"png"`, by default the one of `path`
+    #[cfg(feature = "image")]
+    #[args(format = "None")]
+    fn save(&self, path: String, format: Option<String>) -> PyResult<()> {
+        use ::image::{DynamicImage, ImageBuffer};
+        let mip = &self.0;
+        let image = mip
+            .decode_rgba8()
+            .ok()
+            .and_then(|rgba| ImageBuffer::from_raw(mip.width, mip.height, rgba))
+            .ok_or_else(|| cannot_decode(mip.format))?;
+        save_image(&DynamicImage::ImageRgba8(image), &path, format.as_deref())
+    }
+
+    fn __richcmp__(&self, other: PyRef<Self>, op: CompareOp, py: Python<'_>) -> PyObject {
+        compare(self, &other, op, py)
+    }
+    fn __reduce__(&self, py: Python<'_>) -> PyObject {
+        let data = PyBytes::new(py, &self.0.data);
+        let mip = &self.0;
+        reduce::<Self>(py, (mip.width, mip.height, mip.format, data, mip.id))
     }
 
     fn __repr__(&self) -> PyResult<String> {
+        let format = self.0.format;
+        let layout = match (format.block_size(), format.bytes_per_pixel()) {
+            (Some(block), _) => format!("{} byte blocks", block),
+            (_, Some(pixel)) => format!("{} bytes/pixel", pixel),
+            _ => unreachable!("formats are either compressed or not"),
+        };
+        let alpha = if format.has_alpha() { ", alpha" } else { "" };
         Ok(format!(
-            "PyMipMap: {:?} {}x{} ({} bytes)",
-            self.format,
-            self.width,
-            self.height,
-            self.data.len()
+            "PyMipMap #{}: {:?} {}x{} ({} bytes, {}{})",
+            self.0.id,
+            format,
+            self.0.width,
+            self.0.height,
+            self.0.data.len(),
+            layout,
+            alpha
         ))
     }
 }
 
 impl<'a> From<TextureAtlas<'a>> for PyTextureAtlas {
     fn from(atlas: TextureAtlas<'a>) -> Self {
-        let textures = atlas.0.into_iter().map(Into::into).collect();
-        Self { textures }
+        Self(atlas.into_owned())
+    }
+}
+impl From<PyTextureAtlas> for TextureAtlas<'_> {
+    fn from(atlas: PyTextureAtlas) -> Self {
+        atlas.0
     }
 }
 
 impl<'a> From<Texture<'a>> for PyTexture {
     fn from(tex: Texture<'a>) -> Self {
-        let subtextures = tex.subtextures.into_iter().map(Into::into).collect();
-        Self { subtextures }
+        Self(tex.into_owned())
     }
 }
-impl<'a> From<PyTexture> for Texture<'a> {
+impl From<PyTexture> for Texture<'_> {
     fn from(tex: PyTexture) -> Self {
-        let subtextures = tex.subtextures.into_iter().map(Into::into).collect();
-        Self { subtextures }
+        tex.0
     }
 }
 
 impl<'a> From<super::Subtexture<'a>> for PySubtexture {
     fn from(subtex: super::Subtexture<'a>) -> Self {
-        let mipmaps = subtex.mipmaps.into_iter().map(Into::into).collect();
-        Self { mipmaps }
+        Self(subtex.into_owned())
     }
 }
-impl<'a> From<PySubtexture> for super::Subtexture<'a> {
+impl From<PySubtexture> for super::Subtexture<'_> {
     fn from(subtex: PySubtexture) -> Self {
-        let mipmaps = subtex.mipmaps.into_iter().map(Into::into).collect();
-        Self { mipmaps }
+        subtex.0
     }
 }
 
 impl<'a> From<Mipmap<'a>> for PyMipmap {
-    fn from(sub: Mipmap<'a>) -> Self {
-        let Mipmap {
-            id,
-            width,
-            height,
-            format,
-            data,
-        } = sub;
-        let data = data.into_owned();
-        Self {
-            id,
-            width,
-            height,
-            format,
-            data,
-        }
+    fn from(mip: Mipmap<'a>) -> Self {
+        Self(mip.into_owned())
     }
 }
-impl<'a> From<PyMipmap> for Mipmap<'a> {
+impl From<PyMipmap> for Mipmap<'_> {
     fn from(mip: PyMipmap) -> Self {
-        let PyMipmap {
-            id,
-            width,
-            height,
-            format,
-            data,
-        } = mip;
-        let data = data.into();
-        Self {
-            id,
-            width,
-            height,
-            format,
-            data,
-        }
+        mip.0
     }
 }
 
+#[cfg(feature = "image")]
+fn image_from_pixels(width: u32, height: u32, pixels: Vec<u8>) -> PyResult<::image::DynamicImage> {
+    use ::image::{DynamicImage, ImageBuffer};
+    let len = pixels.len();
+    let count = width as usize * height as usize;
+    let image = match len.checked_div(count) {
+        Some(c) if c * count != len => None,
+        Some(1) => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+        Some(2) => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8),
+        Some(3) => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+        Some(4) => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8),
+        _ => None,
+    };
+    image.ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "{} bytes aren't {}x{} pixels with 1 to 4 channels",
+            len, width, height
+        ))
+    })
+}
+
+#[cfg(feature = "image")]
+fn cannot_encode(format: TextureFormat) -> PyErr {
+    PyValueError::new_err(format!("cannot encode {:?}", format))
+}
+
+#[cfg(feature = "image")]
+fn cannot_decode(format: TextureFormat) -> PyErr {
+    PyValueError::new_err(format!("cannot decode {:?}", format))
+}
+
+/// Saves rows top-down, flipped from the stored order like every other export
+#[cfg(feature = "image")]
+fn save_image(image: &::image::DynamicImage, path: &str, format: Option<&str>) -> PyResult<()> {
+    use ::image::{ImageError, ImageFormat};
+    let format = match format {
+        Some(ext) => ImageFormat::from_extension(ext),
+        None => ImageFormat::from_path(path).ok(),
+    };
+    let format = format.ok_or_else(|| PyValueError::new_err("unknown image format"))?;
+    image
+        .flipv()
+        .save_with_format(path, format)
+        .map_err(|err| match err {
+            ImageError::IoError(err) => err.into(),
+            err => PyValueError::new_err(err.to_string()),
+        })
+}
+
+fn rgba_tuples(rgba: Vec<u8>) -> Vec<(u8, u8, u8, u8)> {
+    rgba.chunks_exact(4)
+        .map(|x| (x[0], x[1], x[2], x[3]))
+        .collect()
+}
+
+/// Invalid or unsupported textures are the caller's fault, anything else is an `OSError`
+fn write_error(err: std::io::Error) -> PyErr {
+    use std::io::ErrorKind;
+    match err.kind() {
+        ErrorKind::InvalidInput | ErrorKind::Unsupported => PyValueError::new_err(err.to_string()),
+        _ => err.into(),
+    }
+}
+
+fn wrap<T: Clone, P: From<T>>(items: &[T]) -> Vec<P> {
+    items.iter().cloned().map(Into::into).collect()
+}
+
+fn unwrap<T: From<P>, P>(items: Vec<P>) -> Vec<T> {
+    items.into_iter().map(Into::into).collect()
+}
+
+/// Wraps the item at a Python index, negative ones count from the end
+fn item<T: Clone, P: From<T>>(items: &[T], index: isize) -> PyResult<P> {
+    let index = match index {
+        i if i < 0 => i + items.len() as isize,
+        i => i,
+    };
+    Some(index)
+        .filter(|&i| i >= 0)
+        .and_then(|i| items.get(i as usize))
+        .map(|x| x.clone().into())
+        .ok_or_else(|| pyo3::exceptions::PyIndexError::new_err("index out of range"))
+}
+
+fn iter<P: IntoPy<PyObject>>(py: Python<'_>, items: Vec<P>) -> PyResult<PyObject> {
+    Ok(items.into_py(py).as_ref(py).iter()?.into())
+}
+
+/// Only `==` and `!=` are supported, the wrappers are compared by value
+fn compare<T: PartialEq>(a: &T, b: &T, op: CompareOp, py: Python<'_>) -> PyObject {
+    match op {
+        CompareOp::Eq => (a == b).into_py(py),
+        CompareOp::Ne => (a != b).into_py(py),
+        _ => py.NotImplemented(),
+    }
+}
+
+/// Pickles a class as a call to its constructor
+fn reduce<T: PyTypeInfo>(py: Python<'_>, args: impl IntoPy<Py<PyTuple>>) -> PyObject {
+    (py.get_type::<T>(), args.into_py(py)).into_py(py)
+}
+
+#[pymethods]
+impl TextureFormat {
+    /// The format stored as `id` in mip headers
+    #[staticmethod]
+    #[pyo3(name = "from_id")]
+    fn py_from_id(id: u32) -> PyResult<Self> {
+        Self::from_id(id).ok_or_else(|| PyValueError::new_err(format!("unknown format id {}", id)))
+    }
+
+    #[getter]
+    fn id(&self) -> u32 {
+        *self as u32
+    }
+    #[getter]
+    #[pyo3(name = "is_compressed")]
+    fn py_is_compressed(&self) -> bool {
+        self.is_compressed()
+    }
+    /// Bytes per 4x4 block, `None` for uncompressed formats
+    #[getter]
+    #[pyo3(name = "block_size")]
+    fn py_block_size(&self) -> Option<usize> {
+        self.block_size()
+    }
+    /// Bytes per pixel, `None` for block compressed formats
+    #[getter]
+    #[pyo3(name = "bytes_per_pixel")]
+    fn py_bytes_per_pixel(&self) -> Option<usize> {
+        self.bytes_per_pixel()
+    }
+    #[getter]
+    #[pyo3(name = "has_alpha")]
+    fn py_has_alpha(&self) -> bool {
+        self.has_alpha()
+    }
+
+    /// Name of the equivalent `DXGI_FORMAT`, e.g. `BC3_UNorm`
+    #[cfg(feature = "ddsfile")]
+    #[pyo3(name = "to_dxgi")]
+    fn py_to_dxgi(&self) -> String {
+        format!("{:?}", self.to_dxgi_format())
+    }
+    /// Name of the equivalent `D3DFORMAT`, `None` for formats only DXGI has
+    #[cfg(feature = "ddsfile")]
+    #[pyo3(name = "to_d3d")]
+    fn py_to_d3d(&self) -> Option<String> {
+        self.to_d3d_format().map(|x| format!("{:?}", x))
+    }
+
+    fn __reduce__(&self, py: Python<'_>) -> PyObject {
+        variant::<Self>(py, format!("{:?}", self))
+    }
+}
+
+#[pymethods]
+impl TextureKind {
+    fn __reduce__(&self, py: Python<'_>) -> PyObject {
+        variant::<Self>(py, format!("{:?}", self))
+    }
+}
+
+/// Pickles an enum as a lookup of its variant
+fn variant<T: PyTypeInfo>(py: Python<'_>, name: String) -> PyObject {
+    let getattr = py.import("builtins").and_then(|x| x.getattr("getattr"));
+    let getattr = getattr.expect("builtins always has getattr");
+    (getattr, (py.get_type::<T>(), name)).into_py(py)
+}
+
 #[pymethods]
 impl PyTextureAtlas {
+    #[new]
+    #[args(textures = "Vec::new()")]
+    fn new(textures: Vec<PyTexture>) -> Self {
+        Self(TextureAtlas(unwrap(textures)))
+    }
+
+    #[getter]
+    fn textures(&self) -> Vec<PyTexture> {
+        wrap(&self.0 .0)
+    }
+    #[setter]
+    fn set_textures(&mut self, textures: Vec<PyTexture>) {
+        self.0 .0 = unwrap(textures);
+    }
+
+    /// Serializes the atlas to the contents of a `_tex.bin`
+    fn to_bytes(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let bytes = self.0.try_to_bytes().map_err(write_error)?;
+        Ok(PyBytes::new(py, &bytes).into())
+    }
+
+    fn write(&self, path: String) -> PyResult<()> {
+        let bytes = self.0.try_to_bytes().map_err(write_error)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    fn __len__(&self) -> usize {
+        self.0 .0.len()
+    }
+    fn __getitem__(&self, index: isize) -> PyResult<PyTexture> {
+        item(&self.0 .0, index)
+    }
+    fn __iter__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        iter(py, self.textures())
+    }
+    fn __richcmp__(&self, other: PyRef<Self>, op: CompareOp, py: Python<'_>) -> PyObject {
+        compare(self, &other, op, py)
+    }
+    fn __reduce__(&self, py: Python<'_>) -> PyObject {
+        reduce::<Self>(py, (self.textures(),))
+    }
+
     fn __repr__(&self) -> PyResult<String> {
-        Ok(format!(
-            "PyTextureAtlas: {} texture(s)",
-            self.textures.len()
-        ))
+        Ok(format!("PyTextureAtlas: {} texture(s)", self.0 .0.len()))
     }
 }
 
@@ -165,21 +470,118 @@ impl<E: std::error::Error> From<ExternalError<E>> for PyErr {
 
 #[pymethods]
 impl PyTexture {
+    #[new]
+    fn new(subtextures: Vec<PySubtexture>) -> Self {
+        Self(Texture {
+            subtextures: unwrap(subtextures),
+        })
+    }
+
+    /// Encodes pixels from any buffer along with `mipmaps - 1` smaller mips, see `PyMipmap.from_pixels`
+    #[cfg(feature = "image")]
+    #[staticmethod]
+    #[args(mipmaps = "1")]
+    fn from_pixels(
+        width: u32,
+        height: u32,
+        pixels: &PyAny,
+        format: TextureFormat,
+        mipmaps: u32,
+    ) -> PyResult<Self> {
+        let image = image_from_pixels(width, height, pixel_bytes(pixels)?)?;
+        Texture::from_image(&image, format, mipmaps)
+            .map(Self)
+            .ok_or_else(|| cannot_encode(format))
+    }
+
+    /// Encodes the image at `path` along with `mipmaps - 1` smaller mips
+    #[cfg(feature = "image")]
+    #[staticmethod]
+    #[args(mipmaps = "1")]
+    fn from_file(path: String, format: TextureFormat, mipmaps: u32) -> PyResult<Self> {
+        let image = ::image::open(path).map_err(ExternalError)?;
+        Texture::from_image(&image, format, mipmaps)
+            .map(Self)
+            .ok_or_else(|| cannot_encode(format))
+    }
+
+    #[getter]
+    fn subtextures(&self) -> Vec<PySubtexture> {
+        wrap(&self.0.subtextures)
+    }
+    #[setter]
+    fn set_subtextures(&mut self, subtextures: Vec<PySubtexture>) {
+        self.0.subtextures = unwrap(subtextures);
+    }
+
+    #[getter]
+    fn kind(&self) -> TextureKind {
+        self.0.kind()
+    }
+
     fn to_dds_bytes(&self) -> PyResult<Vec<u8>> {
-        let tex: Texture<'_> = self.clone().into();
-        let dds = tex.to_dds().map_err(ExternalError)?;
+        let dds = self.0.to_dds().map_err(ExternalError)?;
         let mut vec = vec![];
         dds.write(&mut vec).map_err(ExternalError)?;
         Ok(vec)
     }
+
+    /// Saves the image of a subtexture as displayed, YUV textures are converted to RGBA
+    #[cfg(feature = "image")]
+    #[args(subtexture = "0")]
+    fn save_png(&self, path: String, subtexture: usize) -> PyResult<()> {
+        use pyo3::exceptions::PyIndexError;
+        let images = self.0.to_images();
+        let image = images
+            .get(subtexture)
+            .ok_or_else(|| PyIndexError::new_err("subtexture index out of range"))?
+            .as_ref()
+            .ok_or_else(|| {
+                PyValueError::new_err(format!("cannot decode subtexture {}", subtexture))
+            })?;
+        save_image(image, &path, Some("png"))
+    }
+
+    /// Writes a DDS container with every subtexture and mip as stored, with a DX10 header for
+    /// formats lacking a legacy D3D one such as BC4 to BC7. Raises if ddsfile rejects the layout
+    fn save_dds(&self, path: String) -> PyResult<()> {
+        std::fs::write(path, self.to_dds_bytes()?)?;
+        Ok(())
+    }
+
+    /// Writes a KTX2 container with every subtexture and mip as stored. Raises `ValueError` for
+    /// the packed 16-bit formats RGB5, RGB5A1 and RGBA4, mixed formats or uneven mip counts
+    fn save_ktx2(&self, path: String) -> PyResult<()> {
+        let ktx2 = self.0.to_ktx2().map_err(write_error)?;
+        std::fs::write(path, ktx2)?;
+        Ok(())
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.subtextures.len()
+    }
+    fn __getitem__(&self, index: isize) -> PyResult<PySubtexture> {
+        item(&self.0.subtextures, index)
+    }
+    fn __iter__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        iter(py, self.subtextures())
+    }
+    fn __richcmp__(&self, other: PyRef<Self>, op: CompareOp, py: Python<'_>) -> PyObject {
+        compare(self, &other, op, py)
+    }
+    fn __reduce__(&self, py: Python<'_>) -> PyObject {
+        reduce::<Self>(py, (self.subtextures(),))
+    }
+
     fn __repr__(&self) -> PyResult<String> {
-        let mip = match self.subtextures.get(0).and_then(|x| x.mipmaps.get(0)) {
+        let first = self.0.subtextures.first().and_then(|x| x.mipmaps.first());
+        let mip = match first {
             Some(m) => format!(" {:?} {}x{}", m.format, m.width, m.height),
             None => "".to_string(),
         };
         Ok(format!(
             "PyTexture: {} subtexture(s){}",
-            self.subtextures.len(),
+            self.0.subtextures.len(),
             mip
         ))
     }
@@ -187,40 +589,196 @@ impl PyTexture {
 
 #[pymethods]
 impl PySubtexture {
+    #[new]
+    fn new(mipmaps: Vec<PyMipmap>) -> Self {
+        Self(Subtexture {
+            mipmaps: unwrap(mipmaps),
+        })
+    }
+
+    #[getter]
+    fn mipmaps(&self) -> Vec<PyMipmap> {
+        wrap(&self.0.mipmaps)
+    }
+    #[setter]
+    fn set_mipmaps(&mut self, mipmaps: Vec<PyMipmap>) {
+        self.0.mipmaps = unwrap(mipmaps);
+    }
+
+    /// Decoded pixels of the largest mip, or of both mips combined when they're YUV
+    fn to_rgba(&self) -> Option<Vec<(u8, u8, u8, u8)>> {
+        self.0.decode_rgba8().ok().map(rgba_tuples)
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.mipmaps.len()
+    }
+    fn __getitem__(&self, index: isize) -> PyResult<PyMipmap> {
+        item(&self.0.mipmaps, index)
+    }
+    fn __iter__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        iter(py, self.mipmaps())
+    }
+    fn __richcmp__(&self, other: PyRef<Self>, op: CompareOp, py: Python<'_>) -> PyObject {
+        compare(self, &other, op, py)
+    }
+    fn __reduce__(&self, py: Python<'_>) -> PyObject {
+        reduce::<Self>(py, (self.mipmaps(),))
+    }
+
     fn __repr__(&self) -> PyResult<String> {
         let mip = self
+            .0
             .mipmaps
-            .get(0)
+            .first()
             .map(|m| format!(" {:?} {}x{}", m.format, m.width, m.height))
             .unwrap_or_default();
         Ok(format!(
             "Subtexture: {} mipmap(s){}",
-            self.mipmaps.len(),
+            self.0.mipmaps.len(),
             mip
         ))
     }
 }
 
+create_exception!(
+    txp,
+    ParseError,
+    PyException,
+    "Raised for malformed atlases, `offset` is where parsing failed"
+);
+
+fn parse(data: &[u8]) -> PyResult<PyTextureAtlas> {
+    let err = match TextureAtlas::parse(data) {
+        Ok((_, atlas)) => return Ok(atlas.into()),
+        Err(err) => err,
+    };
+    let (offset, msg) = match crate::error_offset(data, &err) {
+        Some(offset) => (offset, format!("invalid atlas at offset {:#x}", offset)),
+        None => (data.len(), "unexpected end of atlas".to_string()),
+    };
+    Python::with_gil(|py| {
+        let err = ParseError::new_err(msg);
+        err.value(py).setattr("offset", offset)?;
+        Err(err)
+    })
+}
+
+/// Keeps the first `channels` of luma, luma and alpha, RGB or RGBA out of RGBA pixels
+fn select_channels<T: Copy>(rgba: Vec<T>, channels: u32, luma: impl Fn(&[T]) -> T) -> Vec<T> {
+    let pixels = rgba.chunks_exact(4);
+    match channels {
+        1 => pixels.map(luma).collect(),
+        2 => pixels.flat_map(|p| [luma(p), p[3]]).collect(),
+        3 => pixels.flat_map(|p| [p[0], p[1], p[2]]).collect(),
+        _ => rgba,
+    }
+}
+
+/// Wraps native endian pixels into a shaped `memoryview` of struct `format`, e.g. `"B"` or `"f"`
+fn pixel_view(
+    py: Python<'_>,
+    pixels: &[u8],
+    format: &str,
+    shape: (u32, u32, u32),
+) -> PyResult<PyObject> {
+    let view = py
+        .import("builtins")?
+        .getattr("memoryview")?
+        .call1((PyBytes::new(py, pixels),))?;
+    let shape = vec![shape.0, shape.1, shape.2];
+    Ok(view.call_method1("cast", (format, shape))?.into())
+}
+
+/// Copies 8-bit pixels out of any buffer, e.g. `bytes` or a numpy array.
+///
+/// Float buffers (`float32`/`float64`) are taken as normalized and converted.
+#[cfg(feature = "image")]
+fn pixel_bytes(pixels: &PyAny) -> PyResult<Vec<u8>> {
+    let view = pixels
+        .py()
+        .import("builtins")?
+        .getattr("memoryview")?
+        .call1((pixels,))?;
+    let format: String = view.getattr("format")?.extract()?;
+    let raw = view.call_method0("tobytes")?;
+    let raw = raw.downcast::<PyBytes>()?.as_bytes();
+    let unorm = |x: f64| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+    match format.trim_start_matches(['@', '=', '<']) {
+        "B" | "b" | "c" => Ok(raw.to_vec()),
+        "f" => Ok(raw
+            .chunks_exact(4)
+            .map(|x| unorm(f32::from_ne_bytes(std::array::from_fn(|i| x[i])) as f64))
+            .collect()),
+        "d" => Ok(raw
+            .chunks_exact(8)
+            .map(|x| unorm(f64::from_ne_bytes(std::array::from_fn(|i| x[i]))))
+            .collect()),
+        _ => Err(pyo3::exceptions::PyTypeError::new_err(format!(
+            "unsupported pixel format `{}`, use uint8 or floats",
+            format
+        ))),
+    }
+}
+
+/// Copies bytes, bytes-like objects (`bytearray`, `memoryview`...) or what a file-like object reads
+fn input_bytes(input: &PyAny) -> PyResult<Vec<u8>> {
+    if let Ok(bytes) = input.downcast::<PyBytes>() {
+        return Ok(bytes.as_bytes().to_vec());
+    }
+    if input.hasattr("read")? {
+        return input_bytes(input.call_method0("read")?);
+    }
+    // `bytes(x)` would also accept ints, only take objects exposing a buffer
+    let view = input
+        .py()
+        .import("builtins")?
+        .getattr("memoryview")?
+        .call1((input,))?;
+    let bytes = view.call_method0("tobytes")?;
+    Ok(bytes.downcast::<PyBytes>()?.as_bytes().to_vec())
+}
+
+#[cfg(feature = "mmap")]
+#[pyfunction]
+fn read(path: String) -> PyResult<PyTextureAtlas> {
+    let input = crate::mmap::map(path)?;
+    parse(&input)
+}
+
+#[cfg(not(feature = "mmap"))]
 #[pyfunction]
 fn read(path: String) -> PyResult<PyTextureAtlas> {
-    use std::fs::File;
-    use std::io::Read;
-    let mut file = File::open(path)?;
-    let mut input = vec![];
-    file.read_to_end(&mut input)?;
-    let (_, txp) = TextureAtlas::parse(&input).unwrap();
-    Ok(txp.into())
+    let input = std::fs::read(path)?;
+    parse(&input)
+}
+
+/// Parses an atlas out of bytes or any bytes-like object
+#[pyfunction]
+fn loads(data: &PyAny) -> PyResult<PyTextureAtlas> {
+    parse(&input_bytes(data)?)
+}
+
+/// Parses an atlas read from a file-like object, e.g. `open(path, "rb")` or `io.BytesIO`
+#[pyfunction]
+fn load(file: &PyAny) -> PyResult<PyTextureAtlas> {
+    parse(&input_bytes(file)?)
 }
 
 #[pymodule]
-fn txp(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+fn txp(py: Python<'_>, m: &PyModule) -> PyResult<()> {
     pyo3_log::init();
 
     m.add_wrapped(wrap_pyfunction!(self::read))?;
+    m.add_wrapped(wrap_pyfunction!(self::loads))?;
+    m.add_wrapped(wrap_pyfunction!(self::load))?;
+    m.add("ParseError", py.get_type::<ParseError>())?;
     m.add_class::<PyTextureAtlas>()?;
     m.add_class::<PyTexture>()?;
+    m.add_class::<PySubtexture>()?;
     m.add_class::<PyMipmap>()?;
     m.add_class::<TextureFormat>()?;
+    m.add_class::<TextureKind>()?;
 
     Ok(())
 }
@@ -2,19 +2,19 @@ use std::convert::TryInto;
 
 use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::combinator::map;
 use nom::combinator::map_opt;
-use nom::error::ParseError;
+use nom::error::{Error, ErrorKind, ParseError};
 use nom::multi::count;
 use nom::multi::length_data;
 use nom::number::complete::u32;
+use nom::number::Endianness;
 use nom::IResult;
 use nom::Parser;
-use tracing::{debug, instrument, trace};
+use tracing::{debug, trace};
 
 use super::*;
 
-fn parse_magic(id: u8) -> impl Fn(&[u8]) -> IResult<&[u8], nom::number::Endianness> {
+pub(crate) fn parse_magic(id: u8) -> impl Fn(&[u8]) -> IResult<&[u8], nom::number::Endianness> {
     use nom::number::Endianness::*;
     move |i: &[u8]| {
         let (i, res) = alt((tag(&[0x54, 0x58, 0x50, id]), tag(&[id, 0x50, 0x58, 0x54])))(i)?;
@@ -22,6 +22,69 @@ fn parse_magic(id: u8) -> impl Fn(&[u8]) -> IResult<&[u8], nom::number::Endianne
     }
 }
 
+/// Offset of the failure `err` from the start of `input`, `None` if the input simply ran out
+///
+/// ```
+/// let input = b"TXP\x03";
+/// let err = txp::TextureAtlas::parse(input).unwrap_err();
+/// assert_eq!(txp::error_offset(input, &err), Some(4));
+/// ```
+pub fn error_offset(input: &[u8], err: &nom::Err<nom::error::Error<&[u8]>>) -> Option<usize> {
+    match err {
+        // every parser works on a suffix of the input
+        nom::Err::Error(e) | nom::Err::Failure(e) => Some(input.len() - e.input.len()),
+        nom::Err::Incomplete(_) => None,
+    }
+}
+
+/// The header of a texture (`id` 4) or texture array (`id` 5), shared by every reader
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TextureHeader {
+    pub endian: Endianness,
+    pub depth: u32,
+    pub mip_count: u32,
+}
+
+impl TextureHeader {
+    pub(crate) fn parse(id: u8) -> impl Fn(&[u8]) -> IResult<&[u8], TextureHeader> {
+        move |i0: &[u8]| {
+            let (i, endian) = parse_magic(id)(i0)?;
+            let (i, total_mip_count) = u32(endian)(i)?;
+            let (i, mipdata) = u32(endian)(i)?;
+            let depth = if id == 5 { (mipdata & 0xFF00) >> 8 } else { 1 };
+            if depth == 0 {
+                return Err(nom::Err::Error(Error::new(i0, ErrorKind::Verify)));
+            }
+            let mip_count = total_mip_count / depth;
+            debug!(?endian, depth, mip_count);
+            Ok((
+                i,
+                Self {
+                    endian,
+                    depth,
+                    mip_count,
+                },
+            ))
+        }
+    }
+}
+
+/// Parses the texture with magic `id` at `i0`, calling `f` on every mip offset
+///
+/// Returns the mips of each subtexture, in order.
+pub(crate) fn parse_texture<'a, O>(
+    id: u8,
+    i0: &'a [u8],
+    f: impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
+) -> IResult<&'a [u8], Vec<Vec<O>>> {
+    let (i, header) = TextureHeader::parse(id)(i0)?;
+    let (_, subtextures) = count(
+        offset_table(i0, f, header.mip_count.try_into().unwrap(), header.endian),
+        header.depth as usize,
+    )(i)?;
+    Ok((i, subtextures))
+}
+
 impl<'a> TextureAtlas<'a> {
     #[tracing::instrument(name = "atlas", skip(i0))]
     pub fn parse(i0: &'a [u8]) -> IResult<&'a [u8], TextureAtlas<'a>> {
@@ -38,34 +101,19 @@ impl<'a> TextureAtlas<'a> {
 impl<'a> Texture<'a> {
     #[tracing::instrument(name = "texture", skip(i0))]
     pub fn parse(i0: &'a [u8]) -> IResult<&'a [u8], Texture<'a>> {
-        let (i, endian) = parse_magic(4)(i0)?;
-        let (i, mip_count) = u32(endian)(i)?;
-        let (i, unk) = u32(endian)(i)?;
-        debug!(?endian, mip_count, unk);
-        let (_, mipmaps) =
-            offset_table(i0, Mipmap::parse, mip_count.try_into().unwrap(), endian).parse(i)?;
-        Ok((
-            i,
-            Self {
-                subtextures: vec![Subtexture { mipmaps }],
-            },
-        ))
+        Self::parse_id(4, i0)
     }
     #[tracing::instrument(name = "array", skip(i0))]
     pub fn parse_array(i0: &'a [u8]) -> IResult<&'a [u8], Texture<'a>> {
-        use nom::multi::count;
-        let (i, endian) = parse_magic(5)(i0)?;
-        let (i, total_mip_count) = u32(endian)(i)?;
-        let (i, mipdata) = u32(endian)(i)?;
-        let depth = (mipdata & 0xFF00) >> 8;
-        let mip_count = total_mip_count / depth;
-        let (_, subtextures) = count(
-            offset_table(i0, Mipmap::parse, mip_count.try_into().unwrap(), endian)
-                .map(|mipmaps| Subtexture { mipmaps }),
-            depth as usize,
-        )
-        .parse(i)?;
-        //let sides = sides.into_iter().map(|mipmaps| Side { mipmaps }).collect();
+        Self::parse_id(5, i0)
+    }
+
+    fn parse_id(id: u8, i0: &'a [u8]) -> IResult<&'a [u8], Texture<'a>> {
+        let (i, subtextures) = parse_texture(id, i0, Mipmap::parse)?;
+        let subtextures = subtextures
+            .into_iter()
+            .map(|mipmaps| Subtexture { mipmaps })
+            .collect();
         Ok((i, Self { subtextures }))
     }
 }
@@ -85,7 +133,7 @@ where
     }
 }
 
-fn offset_table<'a, F, O, E>(
+pub(crate) fn offset_table<'a, F, O, E>(
     i0: &'a [u8],
     mut f: F,
     cnt: usize,
@@ -96,8 +144,6 @@ where
     E: ParseError<&'a [u8]>,
 {
     move |i: &'a [u8]| {
-        // let (i1, offset) = f1(i)?;
-        let f1 = u32(endian)(i)?;
         let (i1, offsets) = count(u32(endian).map(|x| x as usize), cnt)(i)?;
         let mut res = vec![];
         let mut f0 = |x: &'a [u8]| f.parse(x);
@@ -134,9 +180,8 @@ impl<'a> Mipmap<'a> {
 }
 
 impl TextureFormat {
-    #[tracing::instrument(error)]
-    pub(crate) fn from_id(id: u32) -> Option<Self> {
-        use super::TextureFormat::*;
+    /// The format stored as `id` in mip headers
+    pub fn from_id(id: u32) -> Option<Self> {
         match id {
             0 => Some(Self::A8),
             1 => Some(Self::RGB8),
@@ -187,6 +232,6 @@ mod tests {
 
     #[test]
     fn read_atlas() {
-        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        let (_, _atlas) = TextureAtlas::parse(INPUT).unwrap();
     }
 }
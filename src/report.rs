@@ -0,0 +1,122 @@
+//! Machine readable summaries of atlases, for tooling that shouldn't scrape `metadata`.
+use nom::multi::count;
+use nom::number::complete::u32;
+use nom::IResult;
+use serde::Serialize;
+
+use super::read::parse_magic;
+use super::*;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct AtlasReport {
+    pub textures: Vec<TextureReport>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct TextureReport {
+    pub kind: TextureKind,
+    /// Offset of the texture header from the start of the atlas
+    pub offset: usize,
+    pub subtextures: Vec<SubtextureReport>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct SubtextureReport {
+    pub mipmaps: Vec<MipmapReport>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct MipmapReport {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    /// Offset of the data from the start of the atlas
+    pub offset: usize,
+    /// Length of the data in bytes
+    pub size: usize,
+}
+
+impl AtlasReport {
+    /// Reports on the atlas in `i0`, only the headers are read
+    ///
+    /// ```no_run
+    /// let data = std::fs::read("mikitm001_tex.bin")?;
+    /// let (_, report) = txp::report::AtlasReport::parse(&data).unwrap();
+    /// println!("{}", report.to_json());
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn parse(i0: &[u8]) -> IResult<&[u8], Self> {
+        let (i, index) = TextureAtlasIndex::parse(i0)?;
+        let (o, endian) = parse_magic(3)(i0)?;
+        let (o, map_count) = u32(endian)(o)?;
+        let (o, _unk) = u32(endian)(o)?;
+        let (_, offsets) = count(u32(endian), map_count as usize)(o)?;
+        Ok((i, Self::new(&index, &offsets)))
+    }
+
+    fn new(index: &TextureAtlasIndex, offsets: &[u32]) -> Self {
+        let textures = index
+            .0
+            .iter()
+            .zip(offsets)
+            .map(|(tex, &offset)| TextureReport {
+                kind: tex.kind(),
+                offset: offset as usize,
+                subtextures: tex
+                    .subtextures
+                    .iter()
+                    .map(|sub| SubtextureReport {
+                        mipmaps: sub.mipmaps.iter().map(MipmapReport::from).collect(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self { textures }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("reports always serialize")
+    }
+}
+
+impl From<&MipmapHeader> for MipmapReport {
+    fn from(header: &MipmapHeader) -> Self {
+        Self {
+            id: header.id,
+            width: header.width,
+            height: header.height,
+            format: header.format,
+            offset: header.offset,
+            size: header.len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_atlas() {
+        let input = include_bytes!("../assets/mikitm001_tex.txp");
+        let (_, report) = AtlasReport::parse(input).unwrap();
+        assert_eq!(report.textures.len(), 18);
+        let first = &report.textures[0];
+        assert_eq!((first.kind, first.offset), (TextureKind::Plain, 0x54));
+        let mip = &first.subtextures[0].mipmaps[0];
+        assert_eq!((mip.width, mip.height, mip.offset), (256, 8, 100 + 24));
+        assert_eq!(mip.size, 256 * 8 * 3);
+        assert_eq!(report.textures[1].kind, TextureKind::Mipmapped);
+        let json = report.to_json();
+        assert!(json.contains("\"kind\": \"plain\""));
+    }
+
+    #[test]
+    fn report_yuv() {
+        let input = include_bytes!("../assets/spr_sel_pv616.bin");
+        let (_, report) = AtlasReport::parse(input).unwrap();
+        assert_eq!(report.textures[0].kind, TextureKind::Yuv);
+        assert_eq!(report.textures[1].kind, TextureKind::Plain);
+    }
+}
@@ -0,0 +1,290 @@
+//! Reading atlases from any `Read + Seek` source without loading them whole.
+//!
+//! Only the headers are read upfront, mip data is fetched when asked for.
+use std::io::{self, Read, Seek, SeekFrom};
+
+use nom::branch::alt;
+use nom::number::Endianness;
+use tracing::{debug, trace};
+
+use super::index::*;
+use super::read::TextureHeader;
+use super::*;
+
+/// Lazily reads an atlas from `R`, see [`AtlasReader::new`]
+#[derive(Debug)]
+pub struct AtlasReader<R> {
+    reader: R,
+    base: u64,
+    endian: Endianness,
+    /// Mip headers of every subtexture of every texture
+    pub index: TextureAtlasIndex,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn not_found(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, msg)
+}
+
+impl<R: Read + Seek> AtlasReader<R> {
+    /// Reads the headers of the atlas starting at the current position of `reader`
+    ///
+    /// ```no_run
+    /// let file = std::fs::File::open("mikitm001_tex.bin")?;
+    /// let mut reader = txp::AtlasReader::new(file)?;
+    /// let first = reader.index.mipmap(0, 0, 0).unwrap();
+    /// println!("{}x{} {:?}", first.width, first.height, first.format);
+    /// let mip = reader.read_mipmap(0, 0, 0)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    #[tracing::instrument(name = "atlas_reader", skip(reader))]
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let base = reader.stream_position()?;
+        let mut this = Self {
+            reader,
+            base,
+            endian: Endianness::Little,
+            index: TextureAtlasIndex::default(),
+        };
+        this.endian = this.magic(&[3])?.0;
+        let count = this.u32()?;
+        let _unk = this.u32()?;
+        debug!(?this.endian, count);
+        let offsets = this.offsets(count)?;
+        for offset in offsets {
+            let tex = this.texture(offset)?;
+            this.index.0.push(tex);
+        }
+        Ok(this)
+    }
+
+    fn magic(&mut self, ids: &[u8]) -> io::Result<(Endianness, u8)> {
+        let mut magic = [0; 4];
+        self.reader.read_exact(&mut magic)?;
+        match magic {
+            [0x54, 0x58, 0x50, id] if ids.contains(&id) => Ok((Endianness::Little, id)),
+            [id, 0x50, 0x58, 0x54] if ids.contains(&id) => Ok((Endianness::Big, id)),
+            _ => Err(invalid(format!("invalid magic {:02x?}", magic))),
+        }
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0; 4];
+        self.reader.read_exact(&mut buf)?;
+        Ok(match self.endian {
+            Endianness::Big => u32::from_be_bytes(buf),
+            _ => u32::from_le_bytes(buf),
+        })
+    }
+
+    fn offsets(&mut self, count: u32) -> io::Result<Vec<u32>> {
+        (0..count).map(|_| self.u32()).collect()
+    }
+
+    fn seek(&mut self, offset: u64) -> io::Result<()> {
+        self.reader.seek(SeekFrom::Start(self.base + offset))?;
+        Ok(())
+    }
+
+    fn texture(&mut self, offset: u32) -> io::Result<TextureIndex> {
+        let start = offset as u64;
+        self.seek(start)?;
+        let mut buf = [0; 12];
+        self.reader.read_exact(&mut buf)?;
+        let (_, header) = alt((TextureHeader::parse(4), TextureHeader::parse(5)))(&buf[..])
+            .map_err(|_| invalid(format!("invalid texture header at {:#x}", offset)))?;
+        debug!(offset, header.depth, header.mip_count);
+        let offsets = self.offsets(header.mip_count * header.depth)?;
+        let mip_count = header.mip_count as usize;
+        let mut subtextures = vec![];
+        // one table per subtexture like `parse_texture`, even when they're empty
+        for j in 0..header.depth as usize {
+            let mut mipmaps = vec![];
+            for &mip in &offsets[j * mip_count..][..mip_count] {
+                mipmaps.push(self.mipmap(start + mip as u64)?);
+            }
+            subtextures.push(SubtextureIndex { mipmaps });
+        }
+        Ok(TextureIndex { subtextures })
+    }
+
+    fn mipmap(&mut self, offset: u64) -> io::Result<MipmapHeader> {
+        self.seek(offset)?;
+        self.magic(&[2])?;
+        let width = self.u32()?;
+        let height = self.u32()?;
+        let format = self.u32()?;
+        let format = TextureFormat::from_id(format)
+            .ok_or_else(|| invalid(format!("unknown texture format {}", format)))?;
+        let id = self.u32()?;
+        let len = self.u32()? as usize;
+        trace!(width, height, ?format, id, len);
+        Ok(MipmapHeader {
+            id,
+            width,
+            height,
+            format,
+            offset: offset as usize + 24,
+            len,
+        })
+    }
+
+    fn header(&self, texture: usize, subtexture: usize, mip: usize) -> io::Result<MipmapHeader> {
+        self.index
+            .mipmap(texture, subtexture, mip)
+            .copied()
+            .ok_or_else(|| {
+                not_found(format!(
+                    "no mip {} in texture {}:{}",
+                    mip, texture, subtexture
+                ))
+            })
+    }
+
+    /// Reads the data of a single mip
+    pub fn read_mipmap(
+        &mut self,
+        texture: usize,
+        subtexture: usize,
+        mip: usize,
+    ) -> io::Result<Mipmap<'static>> {
+        let header = self.header(texture, subtexture, mip)?;
+        self.seek(header.offset as u64)?;
+        // the length comes from the file, don't trust it for the allocation
+        let mut data = vec![];
+        (&mut self.reader)
+            .take(header.len as u64)
+            .read_to_end(&mut data)?;
+        if data.len() != header.len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "mip {} in texture {}:{} claims {} bytes, only {} left",
+                    mip,
+                    texture,
+                    subtexture,
+                    header.len,
+                    data.len()
+                ),
+            ));
+        }
+        Ok(Mipmap {
+            id: header.id,
+            width: header.width,
+            height: header.height,
+            format: header.format,
+            data: data.into(),
+        })
+    }
+
+    pub fn read_texture(&mut self, texture: usize) -> io::Result<Texture<'static>> {
+        let shape: Vec<usize> = self
+            .index
+            .0
+            .get(texture)
+            .ok_or_else(|| not_found(format!("no texture {}", texture)))?
+            .subtextures
+            .iter()
+            .map(|x| x.mipmaps.len())
+            .collect();
+        let mut subtextures = vec![];
+        for (j, len) in shape.into_iter().enumerate() {
+            let mipmaps = (0..len)
+                .map(|k| self.read_mipmap(texture, j, k))
+                .collect::<io::Result<_>>()?;
+            subtextures.push(Subtexture { mipmaps });
+        }
+        Ok(Texture { subtextures })
+    }
+
+    /// Reads every texture, equivalent to [`TextureAtlas::parse`] on the whole input
+    pub fn read_atlas(&mut self) -> io::Result<TextureAtlas<'static>> {
+        (0..self.index.0.len())
+            .map(|i| self.read_texture(i))
+            .collect::<io::Result<_>>()
+            .map(TextureAtlas)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const INPUT: &[u8] = include_bytes!("../assets/mikitm001_tex.txp");
+
+    #[test]
+    fn read_headers() {
+        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        let reader = AtlasReader::new(Cursor::new(INPUT)).unwrap();
+        let (_, index) = TextureAtlasIndex::parse(INPUT).unwrap();
+        assert_eq!(reader.index, index);
+        for (tex, headers) in atlas.0.iter().zip(&reader.index.0) {
+            let mip = &tex.subtextures[0].mipmaps[0];
+            let header = headers.subtextures[0].mipmaps[0];
+            assert_eq!((header.width, header.height), (mip.width, mip.height));
+            assert_eq!(header.format, mip.format);
+            assert_eq!(&INPUT[header.offset..][..header.len], &mip.data[..]);
+        }
+    }
+
+    #[test]
+    fn read_on_demand() {
+        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        // Start mid stream to make sure offsets are relative to the atlas
+        let mut input = vec![0xFF; 7];
+        input.extend(INPUT);
+        let mut cursor = Cursor::new(input);
+        cursor.set_position(7);
+        let mut reader = AtlasReader::new(cursor).unwrap();
+        assert_eq!(
+            reader.read_mipmap(1, 0, 3).unwrap(),
+            atlas.0[1].subtextures[0].mipmaps[3]
+        );
+        assert_eq!(reader.read_atlas().unwrap(), atlas);
+        let err = reader.read_mipmap(1, 0, 30).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn read_truncated() {
+        let (_, index) = TextureAtlasIndex::parse(INPUT).unwrap();
+        let header = index.mipmap(1, 0, 0).unwrap();
+        let mut input = INPUT.to_vec();
+        input[header.offset - 4..header.offset].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = AtlasReader::new(Cursor::new(input)).unwrap();
+        let err = reader.read_mipmap(1, 0, 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_array_without_mips() {
+        #[rustfmt::skip]
+        let input = [
+            0x54, 0x58, 0x50, 3, 1, 0, 0, 0, 0, 0, 0, 0, 16, 0, 0, 0,
+            0x54, 0x58, 0x50, 5, 0, 0, 0, 0, 0, 2, 0, 0,
+        ];
+        let reader = AtlasReader::new(Cursor::new(input)).unwrap();
+        let (_, index) = TextureAtlasIndex::parse(&input).unwrap();
+        assert_eq!(reader.index, index);
+        assert_eq!(index.0[0].subtextures.len(), 2);
+    }
+
+    #[test]
+    fn read_empty_array() {
+        #[rustfmt::skip]
+        let input = [
+            0x54, 0x58, 0x50, 3, 1, 0, 0, 0, 0, 0, 0, 0, 16, 0, 0, 0,
+            0x54, 0x58, 0x50, 5, 1, 0, 0, 0, 0, 0, 0, 0, 12, 0, 0, 0,
+        ];
+        let err = AtlasReader::new(Cursor::new(input)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,189 @@
+//! WebAssembly bindings for viewing atlases in a browser.
+//!
+//! Everything works on bytes handed over from JS, e.g. a `Uint8Array` of a dropped file,
+//! there is no filesystem access.
+//!
+//! ```js
+//! const atlas = new Atlas(new Uint8Array(await file.arrayBuffer()));
+//! const image = atlas.image(0, 0);
+//! ctx.putImageData(new ImageData(image.data, image.width, image.height), 0, 0);
+//! ```
+use std::convert::TryInto;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::Clamped;
+
+use super::read::parse_magic;
+use super::*;
+
+#[wasm_bindgen]
+pub struct Atlas(TextureAtlas<'static>);
+
+/// The header of a mip
+#[wasm_bindgen]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MipInfo {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    /// Length of the data in bytes
+    pub size: usize,
+}
+
+/// Decoded RGBA pixels, `data` can be passed to `new ImageData` as is
+#[wasm_bindgen]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    data: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Image {
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Clamped<Vec<u8>> {
+        Clamped(self.data.clone())
+    }
+}
+
+/// Offset of the atlas in either a bare `_tex.bin` or a sprite set, like `batch::detect`
+fn atlas_offset(data: &[u8]) -> Option<usize> {
+    if parse_magic(3)(data).is_ok() {
+        return Some(0);
+    }
+    let raw: [u8; 4] = data.get(4..8)?.try_into().ok()?;
+    [u32::from_le_bytes(raw), u32::from_be_bytes(raw)]
+        .iter()
+        .map(|&x| x as usize)
+        .filter(|&x| x >= 8)
+        .find(|&x| data.get(x..).is_some_and(|x| parse_magic(3)(x).is_ok()))
+}
+
+impl Atlas {
+    fn texture(&self, texture: usize) -> Result<&Texture<'static>, JsError> {
+        let textures = &self.0 .0;
+        textures.get(texture).ok_or_else(|| out_of_range("texture"))
+    }
+
+    fn subtexture(
+        &self,
+        texture: usize,
+        subtexture: usize,
+    ) -> Result<&Subtexture<'static>, JsError> {
+        let tex = self.texture(texture)?;
+        tex.subtextures
+            .get(subtexture)
+            .ok_or_else(|| out_of_range("subtexture"))
+    }
+
+    fn mipmap(
+        &self,
+        texture: usize,
+        subtexture: usize,
+        mip: usize,
+    ) -> Result<&Mipmap<'static>, JsError> {
+        let sub = self.subtexture(texture, subtexture)?;
+        sub.mipmaps.get(mip).ok_or_else(|| out_of_range("mipmap"))
+    }
+}
+
+fn out_of_range(what: &str) -> JsError {
+    JsError::new(&format!("{} index out of range", what))
+}
+
+#[wasm_bindgen]
+impl Atlas {
+    /// Parses a `_tex.bin`, or the atlas embedded in a sprite set
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: &[u8]) -> Result<Atlas, JsError> {
+        let offset = atlas_offset(data).ok_or_else(|| JsError::new("not an atlas"))?;
+        let input = &data[offset..];
+        let (_, atlas) = TextureAtlas::parse(input).map_err(|e| {
+            let at = crate::error_offset(input, &e).map_or(data.len(), |at| offset + at);
+            JsError::new(&format!("invalid atlas at {:#x}", at))
+        })?;
+        Ok(Atlas(atlas.into_owned()))
+    }
+
+    #[wasm_bindgen(getter, js_name = textureCount)]
+    pub fn texture_count(&self) -> usize {
+        self.0 .0.len()
+    }
+
+    #[wasm_bindgen(js_name = subtextureCount)]
+    pub fn subtexture_count(&self, texture: usize) -> Result<usize, JsError> {
+        Ok(self.texture(texture)?.subtextures.len())
+    }
+
+    #[wasm_bindgen(js_name = mipCount)]
+    pub fn mip_count(&self, texture: usize, subtexture: usize) -> Result<usize, JsError> {
+        Ok(self.subtexture(texture, subtexture)?.mipmaps.len())
+    }
+
+    pub fn kind(&self, texture: usize) -> Result<TextureKind, JsError> {
+        Ok(self.texture(texture)?.kind())
+    }
+
+    pub fn mip(&self, texture: usize, subtexture: usize, mip: usize) -> Result<MipInfo, JsError> {
+        let mip = self.mipmap(texture, subtexture, mip)?;
+        Ok(MipInfo {
+            id: mip.id,
+            width: mip.width,
+            height: mip.height,
+            format: mip.format,
+            size: mip.data.len(),
+        })
+    }
+
+    /// Decodes a single mip to RGBA
+    pub fn decode(&self, texture: usize, subtexture: usize, mip: usize) -> Result<Image, JsError> {
+        let mip = self.mipmap(texture, subtexture, mip)?;
+        Ok(Image {
+            width: mip.width,
+            height: mip.height,
+            data: mip.decode_rgba8()?,
+        })
+    }
+
+    /// Decodes the image a subtexture displays, combining both mips of YUV textures
+    pub fn image(&self, texture: usize, subtexture: usize) -> Result<Image, JsError> {
+        let sub = self.subtexture(texture, subtexture)?;
+        let data = sub.decode_rgba8()?;
+        let first = &sub.mipmaps[0];
+        Ok(Image {
+            width: first.width,
+            height: first.height,
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Errors can only be created inside of a JS runtime
+    #[test]
+    fn view_sprite_set() {
+        let atlas = include_bytes!("../assets/spr_sel_pv616.bin");
+        assert_eq!(atlas_offset(atlas), Some(0));
+        assert_eq!(atlas_offset(b"not an atlas"), None);
+        let mut input = vec![0; 16];
+        input[4..8].copy_from_slice(&16u32.to_be_bytes());
+        input.extend(atlas);
+        assert_eq!(atlas_offset(&input), Some(16));
+
+        let atlas = Atlas::new(&input).ok().unwrap();
+        assert_eq!(atlas.kind(0).ok(), Some(TextureKind::Yuv));
+        let image = atlas.image(0, 0).ok().unwrap();
+        assert_eq!((image.width, image.height), (1024, 512));
+        assert_eq!(image.data().len(), 1024 * 512 * 4);
+        let mip = atlas.mip(1, 0, 0).ok().unwrap();
+        assert_eq!((mip.width, mip.format), (2048, TextureFormat::DXT5));
+        assert_eq!(
+            atlas.decode(1, 0, 0).ok().unwrap().data.len(),
+            2048 * 1024 * 4
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use std::io::{self, Write};
+
+use tracing::{debug, trace};
+
+use super::*;
+
+const MAGIC: [u8; 3] = [0x54, 0x58, 0x50];
+
+fn write_u32<W: Write>(w: &mut W, x: u32) -> io::Result<()> {
+    w.write_all(&x.to_le_bytes())
+}
+
+fn write_offsets<W: Write>(
+    w: &mut W,
+    header: usize,
+    sizes: impl Iterator<Item = usize>,
+) -> io::Result<()> {
+    let mut offset = header;
+    for size in sizes {
+        write_u32(w, offset as u32)?;
+        offset += size;
+    }
+    Ok(())
+}
+
+impl TextureAtlas<'_> {
+    /// Checks that every texture can be written, see [`Texture::validate`]
+    pub fn validate(&self) -> io::Result<()> {
+        self.0.iter().try_for_each(Texture::validate)
+    }
+
+    /// Serializes the atlas as a little endian `_tex.bin`
+    ///
+    /// The atlas is validated first, an invalid one fails before anything is written.
+    #[tracing::instrument(name = "atlas", skip(self, w))]
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.validate()?;
+        let count = self.0.len() as u32;
+        debug!(count);
+        w.write_all(&MAGIC)?;
+        w.write_all(&[3])?;
+        write_u32(w, count)?;
+        write_u32(w, count | 0x01010100)?;
+        write_offsets(w, 12 + self.0.len() * 4, self.0.iter().map(Texture::size))?;
+        for tex in &self.0 {
+            tex.write_unchecked(w)?;
+        }
+        Ok(())
+    }
+
+    /// Serializes the atlas to a new buffer
+    ///
+    /// # Panics
+    /// If the atlas is invalid, use [`TextureAtlas::try_to_bytes`] to handle that case.
+    #[deprecated(note = "panics on invalid atlases, use `try_to_bytes` instead")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.try_to_bytes().expect("invalid atlas")
+    }
+
+    /// Serializes the atlas to a new buffer, failing if it is invalid
+    pub fn try_to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(12 + self.0.iter().map(|x| 4 + x.size()).sum::<usize>());
+        self.write(&mut out)?;
+        Ok(out)
+    }
+}
+
+impl Texture<'_> {
+    fn mipmaps(&self) -> impl Iterator<Item = &Mipmap<'_>> {
+        self.subtextures.iter().flat_map(|x| x.mipmaps.iter())
+    }
+
+    fn size(&self) -> usize {
+        12 + self.mipmaps().map(|x| 4 + x.size()).sum::<usize>()
+    }
+
+    fn mip_count(&self) -> u32 {
+        self.subtextures
+            .first()
+            .map(|x| x.mipmaps.len())
+            .unwrap_or(0) as u32
+    }
+
+    /// Checks that there are 1 to 255 subtextures with the same amount of mips, at most 255, and
+    /// that mip ids are either the level or the flat index, returning `InvalidInput` otherwise
+    pub fn validate(&self) -> io::Result<()> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        // both counts are packed into a byte of the header
+        match self.subtextures.len() {
+            0 => return Err(invalid("texture has no subtextures".into())),
+            depth if depth > 0xFF => {
+                return Err(invalid(format!("{} subtextures, at most 255", depth)))
+            }
+            _ => (),
+        }
+        let mip_count = self.mip_count();
+        if mip_count > 0xFF {
+            return Err(invalid(format!("{} mipmaps, at most 255", mip_count)));
+        }
+        if self
+            .subtextures
+            .iter()
+            .any(|x| x.mipmaps.len() as u32 != mip_count)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "all subtextures must have the same amount of mipmaps",
+            ));
+        }
+        for (slice, sub) in self.subtextures.iter().enumerate() {
+            for (level, mip) in sub.mipmaps.iter().enumerate() {
+                let flat = slice as u32 * mip_count + level as u32;
+                if mip.id != level as u32 && mip.id != flat {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "mip {} of subtexture {} has id {}, expected {} or {}",
+                            level, slice, mip.id, level, flat
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes the texture, the counterpart of [`TextureAtlas::write`]
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.validate()?;
+        self.write_unchecked(w)
+    }
+
+    #[tracing::instrument(name = "texture", skip(self, w))]
+    fn write_unchecked<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let depth = self.subtextures.len() as u32;
+        let mip_count = self.mip_count();
+        debug!(depth, mip_count);
+        let id = if depth == 1 { 4 } else { 5 };
+        w.write_all(&MAGIC)?;
+        w.write_all(&[id])?;
+        write_u32(w, depth * mip_count)?;
+        write_u32(w, mip_count | (depth << 8) | 0x01010000)?;
+        let header = 12 + (depth * mip_count) as usize * 4;
+        write_offsets(w, header, self.mipmaps().map(Mipmap::size))?;
+        for mip in self.mipmaps() {
+            mip.write(w)?;
+        }
+        Ok(())
+    }
+}
+
+impl Mipmap<'_> {
+    fn size(&self) -> usize {
+        24 + self.data.len()
+    }
+
+    #[tracing::instrument(name = "mip", skip(self, w))]
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        trace!(self.width, self.height, ?self.format, self.id);
+        w.write_all(&MAGIC)?;
+        w.write_all(&[2])?;
+        write_u32(w, self.width)?;
+        write_u32(w, self.height)?;
+        write_u32(w, self.format as u32)?;
+        write_u32(w, self.id)?;
+        write_u32(w, self.data.len() as u32)?;
+        w.write_all(&self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &[u8] = include_bytes!("../assets/mikitm001_tex.txp");
+    const YUV: &[u8] = include_bytes!("../assets/spr_sel_pv616.bin");
+
+    #[test]
+    fn write_atlas() {
+        let (_, atlas) = TextureAtlas::parse(YUV).unwrap();
+        assert_eq!(atlas.try_to_bytes().unwrap(), YUV);
+    }
+
+    #[test]
+    fn write_multiple_mips() {
+        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        let out = atlas.try_to_bytes().unwrap();
+        assert_eq!(out.len(), INPUT.len());
+        let (_, reparsed) = TextureAtlas::parse(&out).unwrap();
+        assert_eq!(atlas, reparsed);
+    }
+
+    #[test]
+    fn write_array() {
+        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        let mip = atlas.0[0].subtextures[0].clone();
+        let cube = TextureAtlas(vec![Texture {
+            subtextures: vec![mip; 6],
+        }]);
+        let out = cube.try_to_bytes().unwrap();
+        assert_eq!(out[12 + 4 + 3], 5);
+        let (_, reparsed) = TextureAtlas::parse(&out).unwrap();
+        assert_eq!(cube, reparsed);
+    }
+
+    #[test]
+    fn write_invalid_counts() {
+        let (_, atlas) = TextureAtlas::parse(INPUT).unwrap();
+        let sub = &atlas.0[1].subtextures[0];
+        let empty = Texture {
+            subtextures: vec![],
+        };
+        let deep = Texture {
+            subtextures: vec![sub.clone(); 256],
+        };
+        let mip = sub.mipmaps[0].clone();
+        let long = Texture {
+            subtextures: vec![Subtexture {
+                mipmaps: (0..256).map(|id| Mipmap { id, ..mip.clone() }).collect(),
+            }],
+        };
+        for tex in [empty, deep, long] {
+            let err = tex.validate().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn write_invalid_id() {
+        let (_, mut atlas) = TextureAtlas::parse(INPUT).unwrap();
+        atlas.0[1].subtextures[0].mipmaps[2].id = 7;
+        let mut out = vec![];
+        let err = atlas.write(&mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(out.is_empty());
+        assert!(atlas.try_to_bytes().is_err());
+    }
+}
@@ -19,12 +19,7 @@ const BGRA: ImageFormat = ImageFormat {
 
 impl Texture<'_> {
     pub fn is_yuv(&self) -> bool {
-        self.subtextures.len() == 1
-            && self
-                .subtextures
-                .get(0)
-                .map(Subtexture::is_yuv)
-                .unwrap_or_default()
+        self.kind() == TextureKind::Yuv
     }
 }
 
@@ -0,0 +1,134 @@
+# Type stubs for the `txp` extension module, see src/py_ffi.rs
+#
+# Methods marked `image` or `ddsfile` require the crate to be built with that feature.
+from typing import Any, Iterator, List, Optional, Tuple, Union
+
+_Buffer = Union[bytes, bytearray, memoryview, Any]
+
+class ParseError(Exception):
+    """Raised for malformed atlases."""
+
+    offset: int
+    """Offset of the input where parsing failed."""
+
+class TextureFormat:
+    A8: TextureFormat
+    RGB8: TextureFormat
+    RGBA8: TextureFormat
+    RGB5: TextureFormat
+    RGB5A1: TextureFormat
+    RGBA4: TextureFormat
+    DXT1: TextureFormat
+    DXT1a: TextureFormat
+    DXT3: TextureFormat
+    DXT5: TextureFormat
+    ATI1: TextureFormat
+    ATI2: TextureFormat
+    L8: TextureFormat
+    L8A8: TextureFormat
+    BC7: TextureFormat
+    BC6H: TextureFormat
+    @staticmethod
+    def from_id(id: int) -> TextureFormat:
+        """The format stored as `id` in mip headers, raises `ValueError` for unknown ids."""
+    @property
+    def id(self) -> int: ...
+    @property
+    def is_compressed(self) -> bool: ...
+    @property
+    def block_size(self) -> Optional[int]:
+        """Bytes per 4x4 block, `None` for uncompressed formats."""
+    @property
+    def bytes_per_pixel(self) -> Optional[int]:
+        """Bytes per pixel, `None` for block compressed formats."""
+    @property
+    def has_alpha(self) -> bool: ...
+    def to_dxgi(self) -> str:
+        """Name of the equivalent `DXGI_FORMAT`, e.g. `BC3_UNorm`. (ddsfile)"""
+    def to_d3d(self) -> Optional[str]:
+        """Name of the equivalent `D3DFORMAT`, `None` for formats only DXGI has. (ddsfile)"""
+    def __int__(self) -> int: ...
+
+class TextureKind:
+    Plain: TextureKind
+    Mipmapped: TextureKind
+    Cubemap: TextureKind
+    Array: TextureKind
+    Yuv: TextureKind
+    def __int__(self) -> int: ...
+
+class PyMipmap:
+    id: int
+    width: int
+    height: int
+    format: TextureFormat
+    data: List[int]
+    def __init__(
+        self, width: int, height: int, format: TextureFormat, data: _Buffer, id: int = 0
+    ) -> None: ...
+    @staticmethod
+    def from_pixels(
+        width: int, height: int, pixels: _Buffer, format: TextureFormat
+    ) -> PyMipmap:
+        """Encodes 1 to 4 channels of uint8 or normalized float pixels. (image)"""
+    @staticmethod
+    def from_file(path: str, format: TextureFormat) -> PyMipmap:
+        """Encodes the image at `path`. (image)"""
+    def to_rgb(self) -> Optional[List[Tuple[int, int, int]]]: ...
+    def to_rgba(self) -> Optional[List[Tuple[int, int, int, int]]]: ...
+    def to_array(self, channels: int = 4, float32: bool = False) -> Optional[memoryview]:
+        """Decoded pixels shaped `(height, width, channels)`, BC6H keeps its HDR values as `float32`."""
+    def save(self, path: str, format: Optional[str] = None) -> None:
+        """Decodes and saves the mip, `format` defaults to the extension of `path`. (image)"""
+    def __eq__(self, other: object) -> bool: ...
+
+class PySubtexture:
+    mipmaps: List[PyMipmap]
+    def __init__(self, mipmaps: List[PyMipmap]) -> None: ...
+    def to_rgba(self) -> Optional[List[Tuple[int, int, int, int]]]:
+        """Decoded pixels of the largest mip, or of both mips combined when they're YUV."""
+    def __len__(self) -> int: ...
+    def __getitem__(self, index: int) -> PyMipmap: ...
+    def __iter__(self) -> Iterator[PyMipmap]: ...
+    def __eq__(self, other: object) -> bool: ...
+
+class PyTexture:
+    subtextures: List[PySubtexture]
+    @property
+    def kind(self) -> TextureKind: ...
+    def __init__(self, subtextures: List[PySubtexture]) -> None: ...
+    @staticmethod
+    def from_pixels(
+        width: int, height: int, pixels: _Buffer, format: TextureFormat, mipmaps: int = 1
+    ) -> PyTexture:
+        """Encodes pixels along with `mipmaps - 1` smaller mips. (image)"""
+    @staticmethod
+    def from_file(path: str, format: TextureFormat, mipmaps: int = 1) -> PyTexture:
+        """Encodes the image at `path` along with `mipmaps - 1` smaller mips. (image)"""
+    def to_dds_bytes(self) -> List[int]: ...
+    def save_png(self, path: str, subtexture: int = 0) -> None:
+        """Saves a subtexture as displayed, YUV textures are converted to RGBA. (image)"""
+    def save_dds(self, path: str) -> None: ...
+    def save_ktx2(self, path: str) -> None: ...
+    def __len__(self) -> int: ...
+    def __getitem__(self, index: int) -> PySubtexture: ...
+    def __iter__(self) -> Iterator[PySubtexture]: ...
+    def __eq__(self, other: object) -> bool: ...
+
+class PyTextureAtlas:
+    textures: List[PyTexture]
+    def __init__(self, textures: List[PyTexture] = ...) -> None: ...
+    def to_bytes(self) -> bytes:
+        """Serializes the atlas to the contents of a `_tex.bin`."""
+    def write(self, path: str) -> None: ...
+    def __len__(self) -> int: ...
+    def __getitem__(self, index: int) -> PyTexture: ...
+    def __iter__(self) -> Iterator[PyTexture]: ...
+    def __eq__(self, other: object) -> bool: ...
+
+def read(path: str) -> PyTextureAtlas: ...
+def loads(data: _Buffer) -> PyTextureAtlas:
+    """Parses an atlas out of bytes or any bytes-like object."""
+
+def load(file: Any) -> PyTextureAtlas:
+    """Parses an atlas read from a file-like object."""